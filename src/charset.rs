@@ -0,0 +1,218 @@
+//! The set of codepoints to generate, from ranges, named Unicode blocks, strings and text files
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Inclusive codepoint ranges of the named presets
+const PRESETS: [(&str, &[(u32, u32)]); 6] = [
+    ("ascii", &[(0x20, 0x7e)]),
+    // printable ISO 8859-1, without the C0 and C1 control codes
+    ("latin-1", &[(0x20, 0x7e), (0xa0, 0xff)]),
+    ("latin-ext-a", &[(0x100, 0x17f)]),
+    ("greek", &[(0x370, 0x3ff)]),
+    ("cyrillic", &[(0x400, 0x4ff)]),
+    ("box-drawing", &[(0x2500, 0x257f)]),
+];
+
+const DEFAULT_PRESET: &str = "latin-1";
+
+/// VS1 to VS16 and VS17 to VS256
+const VARIATION_SELECTORS: [(u32, u32); 2] = [(0xfe00, 0xfe0f), (0xe0100, 0xe01ef)];
+
+/// Codepoints need 21 bits, the variation selector number of a variant char id goes above them
+const SELECTOR_SHIFT: u32 = 21;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CharsetSource {
+    Ranges(Vec::<(u32, u32)>),
+    /// Every char in the string
+    Text(String),
+    /// Every char in the utf-8 text file
+    File(PathBuf),
+}
+
+
+/// Parse a comma separated list of ranges like U+0020-U+007E, single codepoints and preset names
+pub fn parse_ranges(spec: &str) -> Result<Vec::<(u32, u32)>, String> {
+    let mut res = vec![];
+
+    for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+        if let Some((_, ranges)) = PRESETS.iter().find(|(name, _)| name.eq_ignore_ascii_case(item)) {
+            res.extend_from_slice(ranges);
+            continue;
+        }
+
+        let (first, last) = item.split_once('-').unwrap_or((item, item));
+        let (first, last) = (parse_codepoint(first)?, parse_codepoint(last)?);
+        if first > last {
+            return Err(format!("invalid range '{}', start is after the end", item));
+        }
+        res.push((first, last));
+    }
+
+    if res.is_empty() {
+        return Err(format!("empty charset '{}'", spec));
+    }
+
+    Ok(res)
+}
+
+
+/// U+hex, 0xhex or decimal
+fn parse_codepoint(s: &str) -> Result<u32, String> {
+    let s = s.trim();
+    let value = if let Some(hex) = s.strip_prefix("U+").or(s.strip_prefix("u+")).or(s.strip_prefix("0x")) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+
+    let names : Vec::<&str> = PRESETS.iter().map(|(name, _)| *name).collect();
+    match value {
+        Ok(v) if v <= 0x10ffff => Ok(v),
+        _ => Err(format!("invalid codepoint or preset '{}', presets are {}", s, names.join(", ")))
+    }
+}
+
+
+/// 1 to 256 for VS1 to VS256, None for other chars
+fn selector_number(c: u32) -> Option<u32> {
+    match c {
+        0xfe00..=0xfe0f => Some(c - 0xfe00 + 1),
+        0xe0100..=0xe01ef => Some(c - 0xe0100 + 17),
+        _ => None
+    }
+}
+
+
+/// Char id of the variation sequence of base and selector, the selector number shifted above the codepoint.
+/// None when selector is not a variation selector
+pub fn variant_id(base: u32, selector: u32) -> Option<u32> {
+    selector_number(selector).map(|n| n << SELECTOR_SHIFT | base)
+}
+
+
+/// Base codepoint and variation selector of a char id, the selector is None for plain chars
+pub fn split_id(id: u32) -> (u32, Option<u32>) {
+    let base = id & ((1 << SELECTOR_SHIFT) - 1);
+    let selector = match id >> SELECTOR_SHIFT {
+        0 => None,
+        n @ 1..=16 => Some(VARIATION_SELECTORS[0].0 + n - 1),
+        n => Some(VARIATION_SELECTORS[1].0 + n - 17),
+    };
+    (base, selector)
+}
+
+
+/// Unique chars of text, without control chars like line breaks. A char followed by a variation selector
+/// is the variant id of the sequence, selectors on their own are left out
+fn text_codepoints(text: &str) -> Vec::<u32> {
+    let mut res = vec![];
+    let mut chars = text.chars().filter(|c| !c.is_control()).map(|c| c as u32).peekable();
+
+    while let Some(c) = chars.next() {
+        if selector_number(c).is_some() {
+            continue;
+        }
+
+        match chars.peek().and_then(|&s| variant_id(c, s)) {
+            Some(id) => {
+                chars.next();
+                res.push(id);
+            },
+            None => res.push(c)
+        }
+    }
+
+    res
+}
+
+
+/// Sorted char ids of all sources, the latin-1 preset when there are none
+pub fn codepoints(sources: &[CharsetSource]) -> Result<Vec::<u32>, String> {
+    let mut res = BTreeSet::new();
+
+    let default = [CharsetSource::Ranges(parse_ranges(DEFAULT_PRESET)?)];
+    let sources = if sources.is_empty() { &default[..] } else { sources };
+
+    for source in sources {
+        match source {
+            CharsetSource::Ranges(ranges) => {
+                for &(first, last) in ranges {
+                    res.extend(first..=last);
+                }
+            },
+            CharsetSource::Text(text) => res.extend(text_codepoints(text)),
+            CharsetSource::File(path) => {
+                let text = std::fs::read_to_string(path).map_err(|err| format!("could not read charset file {:?}: {}", path, err))?;
+                res.extend(text_codepoints(&text));
+            }
+        }
+    }
+
+    Ok(res.into_iter().collect())
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(Ok(vec![(0x20, 0x7e)]), parse_ranges("U+0020-U+007E"));
+        assert_eq!(Ok(vec![(0x41, 0x41), (0x61, 0x7a)]), parse_ranges("U+41, 0x61-122"));
+        assert_eq!(Ok(vec![(0x370, 0x3ff), (0x2500, 0x257f)]), parse_ranges("Greek,box-drawing"));
+
+        assert!(parse_ranges("U+7E-U+20").is_err());
+        assert!(parse_ranges("U+110000").is_err());
+        assert!(parse_ranges("klingon").is_err());
+        assert!(parse_ranges(",").is_err());
+    }
+
+    #[test]
+    fn default_has_no_control_codes() {
+        let chars = codepoints(&[]).unwrap();
+
+        assert_eq!(95 + 96, chars.len());
+        assert!(chars.iter().all(|&c| !char::from_u32(c).unwrap().is_control()));
+    }
+
+    #[test]
+    fn unique_sorted_chars() {
+        let sources = [
+            CharsetSource::Text("hello\nwörld".to_string()),
+            CharsetSource::Ranges(vec![(0x61, 0x65)]),
+        ];
+        let expected : Vec::<u32> = "abcdehlorwö".chars().map(|c| c as u32).collect();
+
+        assert_eq!(Ok(expected), codepoints(&sources));
+    }
+
+    #[test]
+    fn variation_sequences() {
+        let chars = codepoints(&[CharsetSource::Text("\u{2764}\u{fe0f}\u{845b}\u{e0100}\u{fe0e}\u{2764}".to_string())]).unwrap();
+
+        assert_eq!(vec![0x2764, 16 << 21 | 0x2764, 17 << 21 | 0x845b], chars);
+        assert_eq!((0x2764, None), split_id(chars[0]));
+        assert_eq!((0x2764, Some(0xfe0f)), split_id(chars[1]));
+        assert_eq!((0x845b, Some(0xe0100)), split_id(chars[2]));
+        assert_eq!(Some(256 << 21 | 0x10ffff), variant_id(0x10ffff, 0xe01ef));
+        assert_eq!((0x10ffff, Some(0xe01ef)), split_id(256 << 21 | 0x10ffff));
+        assert_eq!(None, variant_id(0x41, 0x42));
+    }
+
+    #[test]
+    fn text_file() {
+        let path = std::env::temp_dir().join(format!("rust-sdf-tool-charset-{}.txt", std::process::id()));
+        std::fs::write(&path, "Ωmega\r\nΩ").unwrap();
+
+        let chars = codepoints(&[CharsetSource::File(path.clone())]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Ok(vec![0x61, 0x65, 0x67, 0x6d, 0x3a9]), chars);
+        assert!(codepoints(&[CharsetSource::File(path)]).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+use crate::{RenderMode, EdgeMode};
+use crate::fntfont::FntFormat;
+use crate::charset::{self, CharsetSource};
+use crate::pack::{AtlasSize, PackHeuristic, SortOrder};
+
+pub const USAGE: &str = "Usage: rust-sdf-tool --font <path> --size <px> [options]
+
+Options:
+  -f, --font <path>        font file to generate from
+  -i, --face-index <n>     face index in the font file, default 0
+  -s, --size <px>          pixel size of the generated font, can also be given as a positional argument
+  -o, --out-dir <dir>      directory the .fnt and .png files are written to, default current directory
+  -n, --name <name>        base name for the output files, default <family name>_<size>
+  -c, --charset <list>     comma separated codepoint ranges like U+0020-U+007E and presets: ascii, latin-1,
+                           latin-ext-a, greek, cyrillic, box-drawing. Default latin-1
+      --chars <string>     generate the chars in the string
+      --charset-file <path>
+                           generate the chars in the utf-8 text file
+                           --charset, --chars and --charset-file can be repeated and combined
+                           a char followed by a variation selector in --chars or a charset file generates the
+                           glyph of the sequence, with id <selector number> << 21 | <codepoint>. VS1 is 1, VS17 is 17
+  -p, --padding <px>       padding on each side of a glyph in output pixels, default size / 8
+      --spread <px>        max distance encoded in the distance field in output pixels, default size / 4
+  -u, --upscale <n>        render bitmaps n times larger and downsample to size after computing distances, default 1
+      --format <format>    format of the font file, text, binary or xml .fnt, or json in the msdf-atlas-gen
+                           layout, default text
+  -m, --mode <mode>        raster, sdf, vector-sdf, msdf or mtsdf, default raster
+  -j, --jobs <n>           number of threads generating glyphs, default number of cpu cores
+      --edge <edge>        how distances are measured from the rendered bitmap, pixel uses whole pixels,
+                           coverage uses the anti aliasing to place the edge inside pixels, default coverage
+      --atlas-size <size>  page size as <size>, <width>x<height> or auto for the smallest size
+                           that fits all glyphs on one page, default 512
+      --max-atlas-size <px>
+                           largest page size auto can pick, the page size when the glyphs need more than one page,
+                           default 4096
+      --npot               let auto pick sizes that are not powers of two
+      --pack <heuristic>   how glyphs are placed on the page, short-side, long-side, area or bottom-left,
+                           default short-side
+      --sort <order>       order glyphs are packed in, height or area, default height
+  -h, --help               print this message
+";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub font: PathBuf,
+    pub face_index: isize,
+    pub size: u32,
+    pub out_dir: PathBuf,
+    pub name: Option<String>,
+    pub charset: Vec::<CharsetSource>,
+    pub padding: Option<u32>,
+    pub spread: Option<u32>,
+    pub upscale: u32,
+    pub jobs: Option<usize>,
+    pub format: FntFormat,
+    pub mode: RenderMode,
+    pub edge: EdgeMode,
+    pub atlas_size: AtlasSize,
+    pub max_atlas_size: u32,
+    pub npot: bool,
+    pub pack: PackHeuristic,
+    pub sort: SortOrder,
+}
+
+
+impl Args {
+
+    /// Parse the arguments, without the program name. Returns Err with a message on invalid or missing arguments
+    /// and Ok(None) when help was requested.
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Option<Args>, String> {
+
+        let mut font = None;
+        let mut face_index = 0;
+        let mut size = None;
+        let mut out_dir = PathBuf::from(".");
+        let mut name = None;
+        let mut charset = vec![];
+        let mut padding = None;
+        let mut spread = None;
+        let mut upscale = 1;
+        let mut jobs = None;
+        let mut format = FntFormat::Text;
+        let mut mode = RenderMode::Raster;
+        let mut edge = EdgeMode::Coverage;
+        let mut atlas_size = AtlasSize::Fixed(512, 512);
+        let mut max_atlas_size = 4096;
+        let mut npot = false;
+        let mut pack = PackHeuristic::BestShortSideFit;
+        let mut sort = SortOrder::Height;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+
+            // support both "--flag value" and "--flag=value"
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((f, v)) if arg.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg.clone(), None)
+            };
+
+            let mut value = |flag: &str| -> Result<String, String> {
+                match inline_value.clone() {
+                    Some(v) => Ok(v),
+                    None => args.next().ok_or(format!("missing value for '{}'", flag))
+                }
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "-f" | "--font" => font = Some(PathBuf::from(value(&flag)?)),
+                "-i" | "--face-index" => face_index = parse_num(&flag, &value(&flag)?)?,
+                "-s" | "--size" => size = Some(parse_num(&flag, &value(&flag)?)?),
+                "-o" | "--out-dir" => out_dir = PathBuf::from(value(&flag)?),
+                "-n" | "--name" => name = Some(value(&flag)?),
+                "-c" | "--charset" => charset.push(CharsetSource::Ranges(charset::parse_ranges(&value(&flag)?)?)),
+                "--chars" => charset.push(CharsetSource::Text(value(&flag)?)),
+                "--charset-file" => charset.push(CharsetSource::File(PathBuf::from(value(&flag)?))),
+                "-p" | "--padding" => padding = Some(parse_num(&flag, &value(&flag)?)?),
+                "--spread" => spread = Some(parse_num(&flag, &value(&flag)?)?),
+                "-u" | "--upscale" => upscale = parse_num(&flag, &value(&flag)?)?,
+                "-j" | "--jobs" => jobs = Some(parse_num(&flag, &value(&flag)?)?),
+                "--format" => format = value(&flag)?.parse()?,
+                "-m" | "--mode" => mode = value(&flag)?.parse()?,
+                "--edge" => edge = value(&flag)?.parse()?,
+                "--atlas-size" => atlas_size = value(&flag)?.parse()?,
+                "--max-atlas-size" => max_atlas_size = parse_num(&flag, &value(&flag)?)?,
+                "--npot" => npot = true,
+                "--pack" => pack = value(&flag)?.parse()?,
+                "--sort" => sort = value(&flag)?.parse()?,
+                f if f.starts_with('-') => return Err(format!("unknown option '{}'", f)),
+                // positional size, kept so old scripts calling "rust-sdf-tool 32" still work
+                _ => size = Some(parse_num("size", &arg)?)
+            }
+        }
+
+        let size : u32 = size.ok_or("no size given")?;
+        if size == 0 {
+            return Err("size has to be larger than 0".to_string());
+        }
+
+        if upscale == 0 {
+            return Err("upscale has to be larger than 0".to_string());
+        }
+
+        if max_atlas_size == 0 {
+            return Err("max atlas size has to be larger than 0".to_string());
+        }
+
+        if jobs == Some(0) {
+            return Err("jobs has to be larger than 0".to_string());
+        }
+
+        Ok(Some(Args {
+            font: font.ok_or("no font given")?,
+            face_index,
+            size,
+            out_dir,
+            name,
+            charset,
+            padding,
+            spread,
+            upscale,
+            jobs,
+            format,
+            mode,
+            edge,
+            atlas_size,
+            max_atlas_size,
+            npot,
+            pack,
+            sort,
+        }))
+    }
+}
+
+
+fn parse_num<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value '{}' for '{}'", value, flag))
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        Args::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn parse_all_flags() {
+        let args = parse(&["-f", "fonts/a.ttf", "--face-index", "1", "--size=48", "-o", "out", "-n", "ui", "-c", "ascii,U+400-U+4FF", "--chars", "€", "--charset-file", "chars.txt", "-p", "2", "--spread", "6", "-u", "4", "-j", "3", "--format", "binary", "--mode", "sdf", "--edge", "pixel", "--atlas-size", "1024x256", "--max-atlas-size", "2048", "--npot", "--pack", "area", "--sort=area"]).unwrap().unwrap();
+
+        assert_eq!(PathBuf::from("fonts/a.ttf"), args.font);
+        assert_eq!(1, args.face_index);
+        assert_eq!(48, args.size);
+        assert_eq!(PathBuf::from("out"), args.out_dir);
+        assert_eq!(Some("ui".to_string()), args.name);
+        assert_eq!(vec![
+            CharsetSource::Ranges(vec![(0x20, 0x7e), (0x400, 0x4ff)]),
+            CharsetSource::Text("€".to_string()),
+            CharsetSource::File(PathBuf::from("chars.txt")),
+        ], args.charset);
+        assert_eq!(Some(2), args.padding);
+        assert_eq!(Some(6), args.spread);
+        assert_eq!(4, args.upscale);
+        assert_eq!(Some(3), args.jobs);
+        assert_eq!(FntFormat::Binary, args.format);
+        assert_eq!(RenderMode::Sdf, args.mode);
+        assert_eq!(EdgeMode::Pixel, args.edge);
+        assert_eq!(AtlasSize::Fixed(1024, 256), args.atlas_size);
+        assert_eq!(2048, args.max_atlas_size);
+        assert!(args.npot);
+        assert_eq!(PackHeuristic::BestAreaFit, args.pack);
+        assert_eq!(SortOrder::Area, args.sort);
+    }
+
+    #[test]
+    fn parse_positional_size() {
+        let args = parse(&["--font", "a.ttf", "32"]).unwrap().unwrap();
+        assert_eq!(32, args.size);
+        assert_eq!(PathBuf::from("."), args.out_dir);
+        assert_eq!(None, args.padding);
+        assert!(args.charset.is_empty());
+        assert_eq!(1, args.upscale);
+        assert_eq!(None, args.jobs);
+        assert_eq!(FntFormat::Text, args.format);
+        assert_eq!(RenderMode::Raster, args.mode);
+        assert_eq!(EdgeMode::Coverage, args.edge);
+        assert_eq!(AtlasSize::Fixed(512, 512), args.atlas_size);
+        assert!(!args.npot);
+        assert_eq!(PackHeuristic::BestShortSideFit, args.pack);
+        assert_eq!(SortOrder::Height, args.sort);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse(&["32"]).is_err());
+        assert!(parse(&["-f", "a.ttf"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "abc"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "32", "--bogus"]).is_err());
+        assert!(parse(&["-f"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "32", "-u", "0"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "32", "-j", "0"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "32", "--atlas-size", "big"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "32", "-c", "U+20-"]).is_err());
+        assert!(parse(&["-f", "a.ttf", "-s", "32", "-m", "psdf"]).is_err());
+        assert_eq!(Ok(None), parse(&["-h"]));
+    }
+}
@@ -0,0 +1,353 @@
+//! Binary BMFont format version 3. A "BMF" header followed by typed blocks, all little endian.
+//! https://www.angelcode.com/products/bmfont/doc/file_format.html#bin
+//! The format has no block for the distance field type and range.
+
+use super::*;
+
+const INFO_BLOCK: u8 = 1;
+const COMMON_BLOCK: u8 = 2;
+const PAGES_BLOCK: u8 = 3;
+const CHARS_BLOCK: u8 = 4;
+const KERNING_BLOCK: u8 = 5;
+
+// bit fields are numbered from the most significant bit, like the C bit fields of the BMFont blocks
+const INFO_SMOOTH: u8 = 0x80;
+const INFO_UNICODE: u8 = 0x40;
+const INFO_ITALIC: u8 = 0x20;
+const INFO_BOLD: u8 = 0x10;
+const COMMON_PACKED: u8 = 0x01;
+
+
+pub fn to_bytes(font: &FontInfo) -> Vec::<u8> {
+    let mut res = b"BMF".to_vec();
+    res.push(3);
+
+    write_block(&mut res, INFO_BLOCK, &info_block(font));
+    write_block(&mut res, COMMON_BLOCK, &common_block(font));
+    write_block(&mut res, PAGES_BLOCK, &pages_block(&font.pages));
+    write_block(&mut res, CHARS_BLOCK, &chars_block(&font.pages));
+
+    // the kerning block is left out when there are no pairs
+    if !font.kernings.is_empty() {
+        write_block(&mut res, KERNING_BLOCK, &kerning_block(&font.kernings));
+    }
+
+    res
+}
+
+
+fn write_block(out: &mut Vec::<u8>, block_type: u8, data: &[u8]) {
+    out.push(block_type);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+}
+
+
+fn info_block(font: &FontInfo) -> Vec::<u8> {
+    let mut res = vec![];
+    res.extend((font.size as i16).to_le_bytes());
+
+    let flag = |set: bool, bit: u8| if set { bit } else { 0 };
+    res.push(flag(font.smooth != 0, INFO_SMOOTH)
+        | flag(font.unicode != 0, INFO_UNICODE)
+        | flag(font.italic, INFO_ITALIC)
+        | flag(font.bold, INFO_BOLD));
+
+    // charset is only used for non unicode fonts
+    res.push(0);
+    res.extend((font.stretch_h as u16).to_le_bytes());
+    res.push(font.aa as u8);
+    res.extend(font.padding.map(|p| p as u8));
+    res.extend(font.spacing.map(|s| s as u8));
+    // outline thickness
+    res.push(0);
+
+    res.extend(font.face.as_bytes());
+    res.push(0);
+    res
+}
+
+
+fn common_block(font: &FontInfo) -> Vec::<u8> {
+    let mut res = vec![];
+    for v in [font.line_height, font.base, font.scale_w, font.scale_h, font.pages.len() as i32] {
+        res.extend((v as u16).to_le_bytes());
+    }
+
+    res.push(if font.packed != 0 { COMMON_PACKED } else { 0 });
+
+    // alpha, red, green and blue channel content, 0 is the glyph
+    res.extend([0, 0, 0, 0]);
+    res
+}
+
+
+/// Page file names, each null terminated. The format requires all names to have the same length
+fn pages_block(pages: &[Page]) -> Vec::<u8> {
+    let mut res = vec![];
+    for p in pages {
+        res.extend(p.file.as_bytes());
+        res.push(0);
+    }
+    res
+}
+
+
+fn chars_block(pages: &[Page]) -> Vec::<u8> {
+    let mut res = vec![];
+    for c in pages.iter().flat_map(|p| &p.chars) {
+        res.extend(c.id.to_le_bytes());
+        for v in [c.x, c.y, c.width, c.height] {
+            res.extend((v as u16).to_le_bytes());
+        }
+        for v in [c.xoffset, c.yoffset, c.xadvance] {
+            res.extend((v as i16).to_le_bytes());
+        }
+        res.push(c.page as u8);
+        res.push(c.chnl as u8);
+    }
+    res
+}
+
+
+fn kerning_block(kernings: &[KerningInfo]) -> Vec::<u8> {
+    let mut res = vec![];
+    for k in kernings {
+        res.extend(k.first.to_le_bytes());
+        res.extend(k.second.to_le_bytes());
+        res.extend((k.amount as i16).to_le_bytes());
+    }
+    res
+}
+
+
+pub fn parse(bytes: &[u8]) -> Result<FontInfo, String> {
+    if bytes.len() < 4 || &bytes[0..3] != b"BMF" {
+        return Err("not a binary BMFont file".to_string());
+    }
+    if bytes[3] != 3 {
+        return Err(format!("unsupported binary BMFont version {}", bytes[3]));
+    }
+
+    let mut font = FontInfo::default();
+    let mut chars = vec![];
+
+    let mut i = 4;
+    while i < bytes.len() {
+        let header = bytes.get(i..i + 5).ok_or("truncated block header")?;
+        let len = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+        let data = bytes.get(i + 5..i + 5 + len).ok_or("truncated block")?;
+        let mut r = Reader { data, pos: 0 };
+
+        match header[0] {
+            INFO_BLOCK => {
+                font.size = r.i16()? as i32;
+                let bits = r.u8()?;
+                font.smooth = (bits & INFO_SMOOTH != 0) as i32;
+                font.unicode = (bits & INFO_UNICODE != 0) as i32;
+                font.italic = bits & INFO_ITALIC != 0;
+                font.bold = bits & INFO_BOLD != 0;
+                r.u8()?; // charset
+                font.stretch_h = r.u16()? as i32;
+                font.aa = r.u8()? as i32;
+                for p in &mut font.padding {
+                    *p = r.u8()? as i32;
+                }
+                for s in &mut font.spacing {
+                    *s = r.u8()? as i32;
+                }
+                r.u8()?; // outline
+                font.face = r.string()?;
+            },
+            COMMON_BLOCK => {
+                font.line_height = r.u16()? as i32;
+                font.base = r.u16()? as i32;
+                font.scale_w = r.u16()? as i32;
+                font.scale_h = r.u16()? as i32;
+                r.u16()?; // page count, from the pages block
+                font.packed = (r.u8()? & COMMON_PACKED != 0) as i32;
+            },
+            PAGES_BLOCK => {
+                while r.pos < data.len() {
+                    font.pages.push(Page {
+                        id: font.pages.len() as i32,
+                        file: r.string()?,
+                        chars: vec![],
+                        image: ImageBuffer::new(0, 0),
+                    });
+                }
+            },
+            CHARS_BLOCK => {
+                while r.pos < data.len() {
+                    chars.push(FontCharInfo {
+                        id: r.u32()?,
+                        x: r.u16()? as u32,
+                        y: r.u16()? as u32,
+                        width: r.u16()? as u32,
+                        height: r.u16()? as u32,
+                        xoffset: r.i16()? as i32,
+                        yoffset: r.i16()? as i32,
+                        xadvance: r.i16()? as i32,
+                        page: r.u8()? as i32,
+                        chnl: r.u8()? as i32,
+                    });
+                }
+            },
+            KERNING_BLOCK => {
+                while r.pos < data.len() {
+                    font.kernings.push(KerningInfo {
+                        first: r.u32()?,
+                        second: r.u32()?,
+                        amount: r.i16()? as i32,
+                    });
+                }
+            },
+            // unknown blocks are skipped
+            _ => {}
+        }
+
+        i += 5 + len;
+    }
+
+    add_chars(&mut font, chars)?;
+    Ok(font)
+}
+
+
+/// Little endian values from a block
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self.data.get(self.pos..self.pos + N).ok_or("block is shorter than its fields")?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    /// Null terminated utf-8 string
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.data[self.pos..].iter().position(|&b| b == 0).ok_or("string is not null terminated")?;
+        let s = String::from_utf8_lossy(&self.data[self.pos..self.pos + len]).to_string();
+        self.pos += len + 1;
+        Ok(s)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // block type and data of each block after the header
+    fn blocks(bytes: &[u8]) -> Vec::<(u8, &[u8])> {
+        let mut res = vec![];
+        let mut i = 4;
+        while i < bytes.len() {
+            let len = u32::from_le_bytes(bytes[i + 1..i + 5].try_into().unwrap()) as usize;
+            res.push((bytes[i], &bytes[i + 5..i + 5 + len]));
+            i += 5 + len;
+        }
+        assert_eq!(bytes.len(), i);
+        res
+    }
+
+    #[test]
+    fn block_layout() {
+        let font = sample_font_info();
+        let bytes = to_bytes(&font);
+
+        assert_eq!(b"BMF\x03", &bytes[0..4]);
+
+        let blocks = blocks(&bytes);
+        let types : Vec::<u8> = blocks.iter().map(|b| b.0).collect();
+        assert_eq!(vec![1, 2, 3, 4, 5], types);
+
+        // fixed part of the info block is 14 bytes, then the null terminated name
+        assert_eq!(14 + font.face.len() + 1, blocks[0].1.len());
+        assert_eq!(&(font.size as i16).to_le_bytes(), &blocks[0].1[0..2]);
+        assert_eq!(15, blocks[1].1.len());
+        assert_eq!(b"test_0.png\0", blocks[2].1);
+        assert_eq!(20 * 2, blocks[3].1.len());
+        assert_eq!(10, blocks[4].1.len());
+    }
+
+    #[test]
+    fn bit_fields() {
+        let mut font = sample_font_info();
+        let bytes = to_bytes(&font);
+        let default_blocks = blocks(&bytes);
+
+        // smooth and unicode
+        assert_eq!(0xC0, default_blocks[0].1[2]);
+        assert_eq!(0x00, default_blocks[1].1[10]);
+
+        font.smooth = 0;
+        font.italic = true;
+        font.bold = true;
+        font.packed = 1;
+        let bytes = to_bytes(&font);
+        let blocks = blocks(&bytes);
+        assert_eq!(0x70, blocks[0].1[2]);
+        assert_eq!(0x01, blocks[1].1[10]);
+    }
+
+    #[test]
+    fn parse_bit_fields() {
+        // info block of a bold, smooth, unicode font and a packed common block, laid out as BMFont writes them
+        let mut bytes = b"BMF\x03".to_vec();
+        let info = [&[16u8, 0, 0xD0, 0, 100, 0, 1, 0, 0, 0, 0, 1, 1, 0][..], b"Arial\0"].concat();
+        bytes.push(INFO_BLOCK);
+        bytes.extend((info.len() as u32).to_le_bytes());
+        bytes.extend(info);
+        bytes.push(COMMON_BLOCK);
+        bytes.extend(15u32.to_le_bytes());
+        bytes.extend([16, 0, 13, 0, 0, 1, 0, 1, 0, 0, 0x01, 0, 0, 0, 0]);
+
+        let font = parse(&bytes).unwrap();
+        assert_eq!((1, 1, false, true), (font.smooth, font.unicode, font.italic, font.bold));
+        assert_eq!(1, font.packed);
+        assert_eq!("Arial", font.face);
+    }
+
+    #[test]
+    fn char_fields() {
+        let font = sample_font_info();
+        let bytes = to_bytes(&font);
+        let chars = blocks(&bytes)[3].1;
+
+        let c = &font.pages[0].chars[1];
+        assert_eq!(&c.id.to_le_bytes(), &chars[20..24]);
+        assert_eq!(&(c.x as u16).to_le_bytes(), &chars[24..26]);
+        assert_eq!(&(c.yoffset as i16).to_le_bytes(), &chars[34..36]);
+    }
+
+    #[test]
+    fn no_kerning_block_without_pairs() {
+        let mut font = sample_font_info();
+        font.kernings.clear();
+
+        let bytes = to_bytes(&font);
+        assert!(blocks(&bytes).iter().all(|b| b.0 != KERNING_BLOCK));
+    }
+}
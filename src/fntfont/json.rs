@@ -0,0 +1,170 @@
+//! JSON atlas description in the layout msdf-atlas-gen writes, read by most web and runtime sdf text renderers.
+//! Metrics, plane bounds, advances and kerning are in ems, atlas bounds in pixels with y going down like the page images.
+//! https://github.com/Chlumsky/msdf-atlas-gen#json-layout
+
+use super::*;
+
+
+/// Font metrics in ems, not rounded to whole pixels like the BMFont values
+pub struct EmMetrics {
+    pub line_height: f64,
+    pub ascender: f64,
+    pub descender: f64,
+    pub underline_y: f64,
+    pub underline_thickness: f64,
+    // advance of each char
+    pub advances: Vec::<(u32, f64)>,
+    // first, second and amount of the pairs with kerning
+    pub kernings: Vec::<(u32, u32, f64)>,
+}
+
+
+pub fn to_string(font: &FontInfo, metrics: &EmMetrics) -> String {
+    let size = font.size as f64;
+
+    let mut atlas = vec![];
+    match &font.distance_field {
+        Some(df) => {
+            atlas.push(format!("\"type\":\"{}\"", df.field_type));
+            atlas.push(format!("\"distanceRange\":{}", df.distance_range));
+        },
+        None => atlas.push("\"type\":\"softmask\"".to_string())
+    }
+    atlas.push(format!("\"size\":{}", font.size));
+    atlas.push(format!("\"width\":{}", font.scale_w));
+    atlas.push(format!("\"height\":{}", font.scale_h));
+    atlas.push("\"yOrigin\":\"top\"".to_string());
+
+    // not part of the msdf-atlas-gen layout, which has a single atlas image
+    let multi_page = font.pages.len() > 1;
+    if multi_page {
+        let files : Vec::<String> = font.pages.iter().map(|p| format!("\"{}\"", escape(&p.file))).collect();
+        atlas.push(format!("\"pages\":[{}]", files.join(",")));
+    }
+
+    // y goes down, so everything above the baseline is negative
+    let metrics_obj = format!("{{\"emSize\":1,\"lineHeight\":{},\"ascender\":{},\"descender\":{},\"underlineY\":{},\"underlineThickness\":{}}}",
+                              metrics.line_height, -metrics.ascender, -metrics.descender, -metrics.underline_y, metrics.underline_thickness);
+
+    let mut glyphs = vec![];
+    for c in font.pages.iter().flat_map(|p| &p.chars) {
+        let advance = metrics.advances.iter().find(|(id, _)| *id == c.id).map(|a| a.1).unwrap_or(c.xadvance as f64 / size);
+
+        // the glyph image top in pixels above the baseline
+        let top = (font.base - c.yoffset) as f64;
+
+        let plane = bounds(
+            c.xoffset as f64 / size,
+            (c.height as f64 - top) / size,
+            (c.xoffset as f64 + c.width as f64) / size,
+            -top / size);
+        let atlas = bounds(c.x as f64, (c.y + c.height) as f64, (c.x + c.width) as f64, c.y as f64);
+
+        let page = if multi_page { format!(",\"page\":{}", c.page) } else { "".to_string() };
+
+        glyphs.push(format!("{{{},\"advance\":{},\"planeBounds\":{},\"atlasBounds\":{}{}}}", unicode("unicode", c.id), advance, plane, atlas, page));
+    }
+
+    let kerning : Vec::<String> = metrics.kernings.iter()
+        .map(|&(first, second, amount)| format!("{{{},{},\"advance\":{}}}", unicode("unicode1", first), unicode("unicode2", second), amount))
+        .collect();
+
+    format!("{{\"atlas\":{{{}}},\n\"metrics\":{},\n\"glyphs\":[\n{}\n],\n\"kerning\":[\n{}\n]}}\n",
+            atlas.join(","), metrics_obj, glyphs.join(",\n"), kerning.join(",\n"))
+}
+
+
+fn bounds(left: f64, bottom: f64, right: f64, top: f64) -> String {
+    format!("{{\"left\":{},\"bottom\":{},\"right\":{},\"top\":{}}}", left, bottom, right, top)
+}
+
+
+/// The codepoint of the char id under key. A variant id is not a codepoint, its base codepoint is
+/// written under key and the variation selector in a field of its own, like "variationSelector" for "unicode"
+fn unicode(key: &str, id: u32) -> String {
+    match charset::split_id(id) {
+        (base, None) => format!("\"{}\":{}", key, base),
+        (base, Some(selector)) => format!("\"{}\":{},\"{}\":{}", key, base, key.replace("unicode", "variationSelector"), selector),
+    }
+}
+
+
+fn escape(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        match c {
+            '\\' => res += "\\\\",
+            '"' => res += "\\\"",
+            // control chars can not appear in a json string
+            c if (c as u32) < 0x20 => res += &format!("\\u{:04x}", c as u32),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn metrics() -> EmMetrics {
+        EmMetrics {
+            line_height: 1.25,
+            ascender: 0.75,
+            descender: -0.25,
+            underline_y: -0.125,
+            underline_thickness: 0.0625,
+            advances: vec![(65, 0.5)],
+            kernings: vec![(65, 86, -0.0625)],
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let json = to_string(&sample_font_info(), &metrics());
+        let lines : Vec::<&str> = json.lines().collect();
+
+        assert_eq!("{\"atlas\":{\"type\":\"sdf\",\"distanceRange\":16,\"size\":32,\"width\":64,\"height\":32,\"yOrigin\":\"top\"},", lines[0]);
+        assert_eq!("\"metrics\":{\"emSize\":1,\"lineHeight\":1.25,\"ascender\":-0.75,\"descender\":0.25,\"underlineY\":0.125,\"underlineThickness\":0.0625},", lines[1]);
+
+        // 27 px above the baseline, 12 high and 10 wide from -1
+        assert_eq!("{\"unicode\":65,\"advance\":0.5,\"planeBounds\":{\"left\":-0.03125,\"bottom\":-0.46875,\"right\":0.28125,\"top\":-0.84375},\"atlasBounds\":{\"left\":0,\"bottom\":14,\"right\":10,\"top\":2}},", lines[3]);
+        // advance from the rounded pixel advance when there is no exact one
+        assert!(lines[4].starts_with("{\"unicode\":86,\"advance\":0.28125,"));
+        assert_eq!("{\"unicode1\":65,\"unicode2\":86,\"advance\":-0.0625}", lines[7]);
+        assert_eq!("]}", lines[8]);
+    }
+
+    #[test]
+    fn variant_ids() {
+        let variant = charset::variant_id('A' as u32, 0xfe00).unwrap();
+
+        let mut font = sample_font_info();
+        font.pages[0].chars[0].id = variant;
+        let mut metrics = metrics();
+        metrics.advances = vec![(variant, 0.5)];
+        metrics.kernings = vec![(variant, 86, -0.0625)];
+
+        let json = to_string(&font, &metrics);
+        let lines : Vec::<&str> = json.lines().collect();
+
+        assert!(lines[3].starts_with("{\"unicode\":65,\"variationSelector\":65024,\"advance\":0.5,"));
+        assert_eq!("{\"unicode1\":65,\"variationSelector1\":65024,\"unicode2\":86,\"advance\":-0.0625}", lines[7]);
+    }
+
+    #[test]
+    fn escape_strings() {
+        assert_eq!("a\\\\b \\\"c\\\" \\u000a\\u001f\u{7f}", escape("a\\b \"c\" \n\u{1f}\u{7f}"));
+    }
+
+    #[test]
+    fn raster_is_softmask() {
+        let mut font = sample_font_info();
+        font.distance_field = None;
+
+        let json = to_string(&font, &metrics());
+        assert!(json.starts_with("{\"atlas\":{\"type\":\"softmask\",\"size\":32,"));
+    }
+}
@@ -0,0 +1,759 @@
+use crate::*;
+use crate::pack::*;
+use freetype::face::{KerningMode, StyleFlag};
+use std::io::Write;
+use std::collections::HashMap;
+use std::path::Path;
+
+mod text;
+mod binary;
+mod xml;
+mod json;
+
+// empty pixels between glyphs in the page, so texture filtering does not bleed between them
+const GLYPH_SPACING: u32 = 1;
+
+/// File format of the font description
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FntFormat {
+    Text,
+    Binary,
+    Xml,
+    Json,
+}
+
+impl std::str::FromStr for FntFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(FntFormat::Text),
+            "binary" => Ok(FntFormat::Binary),
+            "xml" => Ok(FntFormat::Xml),
+            "json" => Ok(FntFormat::Json),
+            _ => Err(format!("unknown format '{}', expected text, binary, xml or json", s))
+        }
+    }
+}
+
+
+impl FntFormat {
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FntFormat::Text | FntFormat::Binary | FntFormat::Xml => "fnt",
+            FntFormat::Json => "json",
+        }
+    }
+}
+
+
+/// Errors when a char is too large for the page size
+pub fn write_font_files(face: &freetype::Face, gen_info: GenInfo, layout: LayoutInfo, output: &OutputInfo, chars: Vec::<(CharInfo, RgbaImage)> ) -> Result<(), String> {
+
+    let (line_height, base) = line_metrics(face, gen_info.size);
+
+    let glyphs : Vec::<(u32, u32)> = chars.iter().map(|(c, _)| (c.chr, c.glyph_index)).collect();
+    let kernings_em = kerning_pairs_em(face, &glyphs);
+    let kernings = kerning_pairs(&kernings_em, gen_info.size);
+
+    // layout chars into pages and page images
+    let pages = layout_chars(&output.name, chars, layout, base)?;
+
+    for p in &pages {
+        //out_img = imageops::flip_vertical(&p.image);
+        let path = output.dir.join(&p.file);
+        p.image.save(&path).map_err(|e| format!("could not write {:?}: {}", path, e))?;
+    }
+
+    let font = font_info(face, gen_info, line_height, base, pages, kernings);
+
+    let fnt = match output.format {
+        FntFormat::Text => text::to_string(&font).into_bytes(),
+        FntFormat::Binary => binary::to_bytes(&font),
+        FntFormat::Xml => xml::to_string(&font).into_bytes(),
+        FntFormat::Json => json::to_string(&font, &em_metrics(face, &glyphs, kernings_em)).into_bytes(),
+    };
+
+    let path = output.dir.join(format!("{}.{}", output.name, output.format.extension()));
+    let write_err = |e: std::io::Error| format!("could not write {:?}: {}", path, e);
+    let mut file = std::fs::File::create(&path).map_err(write_err)?;
+    file.write_all(&fnt).map_err(write_err)?;
+    file.flush().map_err(write_err)?;
+
+    // read the written files back, so a writer bug is reported instead of leaving a broken font.
+    // The name and style in the info line can change, the text format has no quote escapes, and there is no json parser
+    if output.format != FntFormat::Json {
+        let written = load_font_file(&path)?;
+        if written.pages != font.pages || written.kernings != font.kernings {
+            return Err(format!("{:?} does not read back as the font that was written", path));
+        }
+    }
+    Ok(())
+}
+
+
+/// Line height and distance from the top of the line to the baseline, in pixels at size.
+/// From the freetype size metrics, which are rounded to whole pixels for scalable fonts
+fn line_metrics(face: &freetype::Face, size: u32) -> (i32, i32) {
+    face.set_pixel_sizes(size, size).unwrap();
+    let metrics = face.size_metrics().unwrap();
+
+    let line_height = (metrics.height as f64 / 64.0).round() as i32;
+    let base = (metrics.ascender as f64 / 64.0).round() as i32;
+    (line_height, base)
+}
+
+
+fn font_info(face: &freetype::Face, gen_info: GenInfo, line_height: i32, base: i32, pages: Vec::<Page>, kernings: Vec::<KerningInfo>) -> FontInfo {
+
+    // all pages have the same size
+    let (scale_w, scale_h) = pages.first().map(|p| p.image.dimensions()).unwrap_or((0, 0));
+    let p = gen_info.glyph_padding() as i32;
+
+    // chars are looked up by unicode codepoint when freetype selected a unicode charmap
+    let charmap = face.raw().charmap;
+    let unicode = !charmap.is_null() && unsafe { (*charmap).encoding } == freetype::ffi::FT_ENCODING_UNICODE;
+
+    // supersampling level, outline modes are not rendered from a bitmap
+    let aa = match gen_info.mode {
+        RenderMode::Raster | RenderMode::Sdf => gen_info.upscale,
+        RenderMode::VectorSdf | RenderMode::Msdf | RenderMode::Mtsdf => 1,
+    };
+
+    FontInfo {
+        face: face.family_name().unwrap_or_default(),
+        size: gen_info.size as i32,
+        bold: face.style_flags().contains(StyleFlag::BOLD),
+        italic: face.style_flags().contains(StyleFlag::ITALIC),
+        charset: "".to_string(),
+        unicode: unicode as i32,
+        stretch_h: 100,
+        smooth: 1,
+        aa: aa as i32,
+        padding: [p; 4],
+        spacing: [GLYPH_SPACING as i32; 2],
+        line_height,
+        base,
+        scale_w: scale_w as i32,
+        scale_h: scale_h as i32,
+        packed: 0,
+        distance_field: gen_info.mode.field_type().map(|field_type| DistanceFieldInfo {
+            field_type: field_type.to_string(),
+            distance_range: gen_info.distance_range(),
+        }),
+        pages,
+        kernings,
+    }
+}
+
+
+/// Pack the chars into page images, placements are the x, y and page of the FontCharInfo.
+/// A new page is started when a char does not fit in any of the previous ones.
+/// The chars stay in the given order in each page, only the packing is in sort order.
+/// base is the distance from the top of the line to the baseline, yoffset is relative to the top of the line.
+/// Errors when a char does not fit on an empty page
+fn layout_chars(name: &str, chars: Vec::<(CharInfo, RgbaImage)>, layout: LayoutInfo, base: i32) -> Result<Vec::<Page>, String> {
+
+    // pack with the spacing on the right and bottom of each glyph. The page is extended by the spacing,
+    // so glyphs can still touch the right and bottom edge
+    let sizes : Vec::<(u32, u32)> = chars.iter().map(|(_, img)| (img.width() + GLYPH_SPACING, img.height() + GLYPH_SPACING)).collect();
+    let pack = |w: u32, h: u32| pack_pages(&sizes, w + GLYPH_SPACING, h + GLYPH_SPACING, layout.heuristic, layout.sort);
+
+    let (page_w, page_h) = match layout.atlas {
+        AtlasSize::Fixed(w, h) => (w, h),
+        AtlasSize::Auto => {
+            let min_area = chars.iter().map(|(_, img)| img.width() as u64 * img.height() as u64).sum();
+            let fits_one_page = |w, h| pack(w, h).is_ok_and(|placements| placements.iter().all(|(page, _)| *page == 0));
+
+            // when not even the max size fits everything, use multiple pages of max size
+            auto_size(fits_one_page, min_area, layout.max_atlas_size, layout.power_of_two)
+                .unwrap_or((layout.max_atlas_size, layout.max_atlas_size))
+        }
+    };
+
+    let placements = pack(page_w, page_h)
+        .map_err(|i| format!("char {} does not fit a {}x{} page", chars[i].0.chr, page_w, page_h))?;
+
+    let page_count = placements.iter().map(|(page, _)| page + 1).max().unwrap_or(1);
+
+    // the binary format needs all page names to have the same length, so the page index is zero padded
+    let digits = (page_count - 1).to_string().len();
+
+    let mut res : Vec::<Page> = (0..page_count).map(|page_id| Page {
+        id: page_id as i32,
+        file: format!("{}_{:0digits$}.png", name, page_id),
+        chars: vec![],
+        image: ImageBuffer::new(page_w, page_h)
+    }).collect();
+
+    for ((chr_info, img), (page_id, rect)) in chars.iter().zip(placements) {
+        let page = &mut res[page_id];
+
+        insert_chr_img(rect.x, rect.y, &mut page.image, img);
+
+        // insert char info to page chars including info about x and y
+        page.chars.push( FontCharInfo {
+            id: chr_info.chr,
+            x: rect.x, // with padding
+            y: rect.y, // with padding
+            width: chr_info.width,
+            height: chr_info.height,
+            xoffset: chr_info.offset_x,
+            yoffset: base - chr_info.offset_y,
+            xadvance: chr_info.advance_x,
+            page: page.id,
+            chnl: 0,
+        });
+    }
+
+    Ok(res)
+}
+
+
+fn insert_chr_img(x: u32, y: u32, res_img: &mut RgbaImage, img: &RgbaImage) {
+    // assume that x + img.width < res_img.width, same with y and height
+    for img_y in 0..img.height() {
+        for img_x in 0..img.width() {
+            res_img.put_pixel(x + img_x, y + img_y, *img.get_pixel(img_x, img_y));
+        }
+    }
+}
+
+
+/// Kerning in pixels at size. Pairs without kerning at this size are left out
+fn kerning_pairs(kernings_em: &[(u32, u32, f64)], size: u32) -> Vec::<KerningInfo> {
+    kernings_em.iter()
+        .map(|&(first, second, amount)| KerningInfo { first, second, amount: (amount * size as f64).round() as i32 })
+        .filter(|k| k.amount != 0)
+        .collect()
+}
+
+
+/// Kerning in ems for every pair of the chars, from freetype. glyphs are the char ids with their glyph index.
+/// The pairs are in the order of the chars, first by the first char
+fn kerning_pairs_em(face: &freetype::Face, glyphs: &[(u32, u32)]) -> Vec::<(u32, u32, f64)> {
+
+    if !face.has_kerning() {
+        return vec![];
+    }
+
+    let em = face.em_size() as f64;
+
+    // chars missing in the font have no kerning
+    let glyphs : Vec::<(u32, u32)> = glyphs.iter().copied().filter(|(_, g)| *g != 0).collect();
+
+    let mut res = vec![];
+    for &(first, first_glyph) in &glyphs {
+        for &(second, second_glyph) in &glyphs {
+            let kerning = face.get_kerning(first_glyph, second_glyph, KerningMode::KerningUnscaled).unwrap();
+            if kerning.x != 0 {
+                res.push((first, second, kerning.x as f64 / em));
+            }
+        }
+    }
+
+    res
+}
+
+
+/// Metrics, advances and kerning in ems from the unscaled font, for the json output. glyphs are the char ids with their glyph index
+fn em_metrics(face: &freetype::Face, glyphs: &[(u32, u32)], kernings: Vec::<(u32, u32, f64)>) -> json::EmMetrics {
+    let em = face.em_size() as f64;
+
+    let advances = glyphs.iter().map(|&(c, glyph_index)| {
+        face.load_glyph(glyph_index, LoadFlag::NO_SCALE).unwrap();
+        (c, face.glyph().advance().x as f64 / em)
+    }).collect();
+
+    json::EmMetrics {
+        line_height: face.height() as f64 / em,
+        ascender: face.ascender() as f64 / em,
+        descender: face.descender() as f64 / em,
+        underline_y: face.underline_position() as f64 / em,
+        underline_thickness: face.underline_thickness() as f64 / em,
+        advances,
+        kernings,
+    }
+}
+
+
+/// Read a .fnt file in any of the BMFont formats and load its page images, which are relative to the .fnt file
+pub fn load_font_file(path: &Path) -> Result<FontInfo, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("could not read {:?}: {}", path, err))?;
+    let mut font = parse_font(&bytes)?;
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for page in &mut font.pages {
+        let file = dir.join(&page.file);
+        page.image = image::open(&file).map_err(|err| format!("could not load page {:?}: {}", file, err))?.to_rgba8();
+    }
+
+    Ok(font)
+}
+
+
+/// Parse a .fnt in the text, XML or binary format, detected from the start of the file. Page images are left empty
+pub fn parse_font(bytes: &[u8]) -> Result<FontInfo, String> {
+    if bytes.starts_with(b"BMF") {
+        return binary::parse(bytes);
+    }
+
+    let s = std::str::from_utf8(bytes).map_err(|_| "font file is neither binary nor utf-8 text".to_string())?;
+    if s.trim_start_matches('\u{feff}').trim_start().starts_with('<') {
+        xml::parse(s)
+    } else {
+        text::parse(s)
+    }
+}
+
+
+/// Block name and its key value pairs, the same for the text and XML formats
+type Tag = (String, HashMap::<String, String>);
+
+fn from_tags(tags: &[Tag]) -> Result<FontInfo, String> {
+    let mut font = FontInfo::default();
+    let mut chars = vec![];
+
+    for (name, attrs) in tags {
+        let text = |key: &str| attrs.get(key).cloned().unwrap_or_default();
+
+        match name.as_str() {
+            "info" => {
+                font.face = text("face");
+                font.size = attr(attrs, "size")?;
+                font.bold = attr::<i32>(attrs, "bold")? != 0;
+                font.italic = attr::<i32>(attrs, "italic")? != 0;
+                font.charset = text("charset");
+                font.unicode = attr(attrs, "unicode")?;
+                font.stretch_h = attr(attrs, "stretchH")?;
+                font.smooth = attr(attrs, "smooth")?;
+                font.aa = attr(attrs, "aa")?;
+                font.padding = attr_list(attrs, "padding")?;
+                font.spacing = attr_list(attrs, "spacing")?;
+            },
+            "common" => {
+                font.line_height = attr(attrs, "lineHeight")?;
+                font.base = attr(attrs, "base")?;
+                font.scale_w = attr(attrs, "scaleW")?;
+                font.scale_h = attr(attrs, "scaleH")?;
+                font.packed = attr(attrs, "packed")?;
+            },
+            "distanceField" => font.distance_field = Some(DistanceFieldInfo {
+                field_type: text("fieldType"),
+                distance_range: attr(attrs, "distanceRange")?,
+            }),
+            "page" => font.pages.push(Page {
+                id: attr(attrs, "id")?,
+                file: text("file"),
+                chars: vec![],
+                image: ImageBuffer::new(0, 0),
+            }),
+            "char" => chars.push(FontCharInfo {
+                id: attr(attrs, "id")?,
+                x: attr(attrs, "x")?,
+                y: attr(attrs, "y")?,
+                width: attr(attrs, "width")?,
+                height: attr(attrs, "height")?,
+                xoffset: attr(attrs, "xoffset")?,
+                yoffset: attr(attrs, "yoffset")?,
+                xadvance: attr(attrs, "xadvance")?,
+                page: attr(attrs, "page")?,
+                chnl: attr(attrs, "chnl")?,
+            }),
+            "kerning" => font.kernings.push(KerningInfo {
+                first: attr(attrs, "first")?,
+                second: attr(attrs, "second")?,
+                amount: attr(attrs, "amount")?,
+            }),
+            // counts, XML containers and blocks we don't use
+            _ => {}
+        }
+    }
+
+    add_chars(&mut font, chars)?;
+    Ok(font)
+}
+
+
+/// Numeric value of key, the default when it is missing
+fn attr<T: std::str::FromStr + Default>(attrs: &HashMap::<String, String>, key: &str) -> Result<T, String> {
+    match attrs.get(key) {
+        Some(v) => v.parse().map_err(|_| format!("invalid value '{}' for {}", v, key)),
+        None => Ok(T::default())
+    }
+}
+
+/// Comma separated values of key, zeros when it is missing
+fn attr_list<const N: usize>(attrs: &HashMap::<String, String>, key: &str) -> Result<[i32; N], String> {
+    let mut res = [0; N];
+    if let Some(v) = attrs.get(key) {
+        let values : Vec::<&str> = v.split(',').collect();
+        if values.len() != N {
+            return Err(format!("expected {} values for {}, got '{}'", N, key, v));
+        }
+        for (r, value) in res.iter_mut().zip(values) {
+            *r = value.trim().parse().map_err(|_| format!("invalid value '{}' for {}", v, key))?;
+        }
+    }
+    Ok(res)
+}
+
+
+/// Put the chars on the pages given by their page field
+fn add_chars(font: &mut FontInfo, chars: Vec::<FontCharInfo>) -> Result<(), String> {
+    for c in chars {
+        let page = font.pages.iter_mut().find(|p| p.id == c.page)
+            .ok_or(format!("char {} is on page {}, which does not exist", c.id, c.page))?;
+        page.chars.push(c);
+    }
+    Ok(())
+}
+
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FontInfo {
+    // INFO
+    face: String,
+    size: i32,
+    bold: bool,
+    italic: bool,
+    charset: String,
+    unicode: i32,
+    stretch_h: i32,
+    smooth: i32,
+    aa: i32,
+    padding: [i32;4],
+    spacing: [i32;2],
+
+    // COMMON
+    line_height: i32,
+    base: i32,
+    scale_w: i32,
+    scale_h: i32,
+    packed: i32,
+
+    // DISTANCE FIELD, None for raster fonts
+    distance_field: Option::<DistanceFieldInfo>,
+
+    // DATA
+    pages: Vec::<Page>,
+    kernings: Vec::<KerningInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceFieldInfo {
+    field_type: String,
+    // total range of distances encoded in 0.0..1.0, in pixels
+    distance_range: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    id: i32,
+    file: String,
+
+     // DATA:
+    chars: Vec::<FontCharInfo>,
+    image: RgbaImage
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontCharInfo {
+    id: u32,
+    x: u32, // position in png
+    y: u32, // position in png
+    width: u32,
+    height: u32,
+    xoffset: i32,
+    yoffset: i32,
+    xadvance: i32,
+    page: i32,
+    chnl: i32,
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KerningInfo {
+    first: u32,
+    second: u32,
+    amount: i32,
+}
+
+
+/// Small font with two chars on one page and one kerning pair, for the writer tests
+#[cfg(test)]
+fn sample_font_info() -> FontInfo {
+    let chr = |id, x| FontCharInfo { id, x, y: 2, width: 10, height: 12, xoffset: -1, yoffset: 3, xadvance: 9, page: 0, chnl: 0 };
+
+    FontInfo {
+        face: "Test".to_string(),
+        size: 32,
+        bold: false,
+        italic: false,
+        charset: "".to_string(),
+        unicode: 1,
+        stretch_h: 100,
+        smooth: 1,
+        aa: 1,
+        padding: [4; 4],
+        spacing: [1, 1],
+        line_height: 39,
+        base: 30,
+        scale_w: 64,
+        scale_h: 32,
+        packed: 0,
+        distance_field: Some(DistanceFieldInfo { field_type: "sdf".to_string(), distance_range: 16 }),
+        pages: vec![Page {
+            id: 0,
+            file: "test_0.png".to_string(),
+            chars: vec![chr(65, 0), chr(86, 20)],
+            image: ImageBuffer::new(64, 32),
+        }],
+        kernings: vec![KerningInfo { first: 65, second: 86, amount: -2 }],
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn char_img(chr: u32, w: u32, h: u32) -> (CharInfo, RgbaImage) {
+        let info = CharInfo {
+            chr,
+            glyph_index: chr,
+            width: w,
+            height: h,
+            advance_x: w as i32,
+            advance_y: 0,
+            padding_x: 0,
+            padding_y: 0,
+            offset_x: 0,
+            offset_y: h as i32,
+        };
+        (info, ImageBuffer::from_pixel(w, h, Rgba([0, 0, 0, 255])))
+    }
+
+    fn layout(page_size: u32) -> LayoutInfo {
+        LayoutInfo {
+            atlas: AtlasSize::Fixed(page_size, page_size),
+            max_atlas_size: 4096,
+            power_of_two: true,
+            heuristic: PackHeuristic::BestShortSideFit,
+            sort: SortOrder::Height,
+        }
+    }
+
+    #[test]
+    fn overflow_to_new_pages() {
+        // two 10x10 glyphs fit next to each other in 21 pixels with the spacing, not three
+        let chars = (0..5).map(|i| char_img(65 + i, 10, 10)).collect();
+        let pages = layout_chars("test", chars, layout(21), 10).unwrap();
+
+        assert_eq!(2, pages.len());
+        assert_eq!("test_1.png", pages[1].file);
+        assert_eq!(4, pages[0].chars.len());
+        assert_eq!(1, pages[1].chars.len());
+        assert!(pages.iter().all(|p| p.chars.iter().all(|c| c.page == p.id)));
+
+        let fnt = text::pages_string(&pages, &[]);
+        assert!(fnt.starts_with("page id=0 file=\"test_0.png\"\npage id=1 file=\"test_1.png\"\nchars count=5\n"));
+    }
+
+    #[test]
+    fn small_glyphs_fill_earlier_pages() {
+        let chars = vec![char_img(65, 20, 20), char_img(66, 20, 20), char_img(67, 4, 4)];
+        let pages = layout_chars("test", chars, layout(26), 20).unwrap();
+
+        assert_eq!(2, pages.len());
+        let ids : Vec::<u32> = pages[0].chars.iter().map(|c| c.id).collect();
+        assert_eq!(vec![65, 67], ids);
+    }
+
+    #[test]
+    fn auto_atlas_size() {
+        let chars = || (0..5).map(|i| char_img(65 + i, 10, 10)).collect();
+
+        let pages = layout_chars("test", chars(), LayoutInfo { atlas: AtlasSize::Auto, ..layout(0) }, 10).unwrap();
+        assert_eq!(1, pages.len());
+        assert_eq!((32, 32), pages[0].image.dimensions());
+
+        let pages = layout_chars("test", chars(), LayoutInfo { atlas: AtlasSize::Auto, power_of_two: false, ..layout(0) }, 10).unwrap();
+        assert_eq!(1, pages.len());
+        assert_eq!((32, 21), pages[0].image.dimensions());
+
+        // falls back to multiple pages of the max size
+        let pages = layout_chars("test", chars(), LayoutInfo { atlas: AtlasSize::Auto, max_atlas_size: 21, ..layout(0) }, 10).unwrap();
+        assert_eq!(2, pages.len());
+        assert_eq!((21, 21), pages[0].image.dimensions());
+    }
+
+    #[test]
+    fn non_square_page() {
+        let chars = (0..5).map(|i| char_img(65 + i, 10, 10)).collect();
+        let pages = layout_chars("test", chars, LayoutInfo { atlas: AtlasSize::Fixed(54, 10), ..layout(0) }, 10).unwrap();
+
+        assert_eq!(1, pages.len());
+        assert_eq!((54, 10), pages[0].image.dimensions());
+    }
+
+    #[test]
+    fn kerning_from_font() {
+        let lib = freetype::Library::init().unwrap();
+        let face = lib.new_face(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf"), 0).unwrap();
+
+        let glyphs : Vec::<(u32, u32)> = "ATV ".chars().map(|c| (c as u32, face.get_char_index(c as usize))).collect();
+        let kernings = kerning_pairs(&kerning_pairs_em(&face, &glyphs), 64);
+
+        let amount = |first: char, second: char| kernings.iter()
+            .find(|k| k.first == first as u32 && k.second == second as u32)
+            .map(|k| k.amount);
+
+        assert!(amount('A', 'V').unwrap() < 0);
+        assert!(amount('A', 'T').unwrap() < 0);
+        assert!(kernings.iter().all(|k| k.amount != 0));
+        assert_eq!(None, amount(' ', ' '));
+
+        let lines = text::kernings_string(&kernings);
+        assert!(lines.starts_with(&format!("kernings count={}\nkerning first=", kernings.len())));
+    }
+
+    #[test]
+    fn variant_glyph_metrics() {
+        let lib = freetype::Library::init().unwrap();
+        let face = lib.new_face(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf"), 0).unwrap();
+
+        // a variation sequence of A whose glyph is the A glyph, its id is not a codepoint of the font
+        let variant = charset::variant_id('A' as u32, 0xfe00).unwrap();
+        let glyphs = [(variant, face.get_char_index('A' as usize)), ('V' as u32, face.get_char_index('V' as usize))];
+
+        let kernings = kerning_pairs_em(&face, &glyphs);
+        assert_eq!(1, kernings.len());
+        assert_eq!((variant, 'V' as u32), (kernings[0].0, kernings[0].1));
+
+        face.load_char('A' as usize, LoadFlag::NO_SCALE).unwrap();
+        let advance = face.glyph().advance().x as f64 / face.em_size() as f64;
+        let metrics = em_metrics(&face, &glyphs, kernings);
+        assert_eq!((variant, advance), metrics.advances[0]);
+    }
+
+    #[test]
+    fn line_metrics_at_size() {
+        let lib = freetype::Library::init().unwrap();
+        let face = lib.new_face(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf"), 0).unwrap();
+
+        // calibri ascender is 0.75 em and line height 1.22 em
+        assert_eq!((39, 24), line_metrics(&face, 32));
+        assert_eq!((78, 48), line_metrics(&face, 64));
+
+        let chars = vec![char_img(65, 10, 20)];
+        let pages = layout_chars("test", chars, layout(64), 24).unwrap();
+        // image top 20 pixels above the baseline
+        assert_eq!(4, pages[0].chars[0].yoffset);
+    }
+
+    #[test]
+    fn info_from_face() {
+        let lib = freetype::Library::init().unwrap();
+        let face = lib.new_face(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibriz.ttf"), 0).unwrap();
+        let gen_info = GenInfo { size: 32, upscale: 2, padding: 4, spread: 8, mode: RenderMode::Sdf, edge: EdgeMode::Coverage };
+
+        let font = font_info(&face, gen_info, 39, 24, vec![], vec![]);
+        assert!(font.bold);
+        assert!(font.italic);
+        assert_eq!(1, font.unicode);
+
+        let fnt = text::to_string(&font);
+        assert_eq!("info face=\"Calibri\" size=32 bold=1 italic=1 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=2 padding=4,4,4,4 spacing=1,1",
+                   fnt.lines().next().unwrap());
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        let font = sample_font_info();
+        let mut expected = font.clone();
+        expected.pages[0].image = ImageBuffer::new(0, 0);
+
+        assert_eq!(Ok(expected.clone()), parse_font(text::to_string(&font).as_bytes()));
+        assert_eq!(Ok(expected.clone()), parse_font(xml::to_string(&font).as_bytes()));
+
+        // binary has no distance field block
+        expected.distance_field = None;
+        assert_eq!(Ok(expected), parse_font(&binary::to_bytes(&font)));
+    }
+
+    #[test]
+    fn parse_bmfont_text() {
+        // as written by AngelCode BMFont, with fields we don't use
+        let fnt = "info face=\"Arial\" size=-32 bold=1 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=0,0,0,0 spacing=1,1 outline=0
+common lineHeight=32 base=26 scaleW=256 scaleH=256 pages=1 packed=0 alphaChnl=1 redChnl=0 greenChnl=0 blueChnl=0
+page id=0 file=\"arial_0.png\"
+chars count=1
+char id=65   x=36    y=0     width=21    height=20    xoffset=-1    yoffset=6     xadvance=19    page=0  chnl=15
+kernings count=1
+kerning first=65  second=86  amount=-2
+";
+        let font = parse_font(fnt.as_bytes()).unwrap();
+
+        assert_eq!("Arial", font.face);
+        assert_eq!(-32, font.size);
+        assert!(font.bold);
+        assert_eq!([1, 1], font.spacing);
+        assert_eq!(26, font.base);
+        assert_eq!(None, font.distance_field);
+        assert_eq!("arial_0.png", font.pages[0].file);
+        assert_eq!(FontCharInfo { id: 65, x: 36, y: 0, width: 21, height: 20, xoffset: -1, yoffset: 6, xadvance: 19, page: 0, chnl: 15 }, font.pages[0].chars[0]);
+        assert_eq!(vec![KerningInfo { first: 65, second: 86, amount: -2 }], font.kernings);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse_font(b"info size=abc").is_err());
+        assert!(parse_font(b"info padding=1,2").is_err());
+        assert!(parse_font(b"char id=65 page=1").is_err());
+        assert!(parse_font(b"<font><info face=\"A\"</font>").is_err());
+        assert!(parse_font(b"BMF\x02").is_err());
+        assert!(parse_font(&binary::to_bytes(&sample_font_info())[..40]).is_err());
+    }
+
+    #[test]
+    fn load_with_pages() {
+        let dir = std::env::temp_dir().join(format!("rust-sdf-tool-load-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut font = sample_font_info();
+        font.pages[0].image.put_pixel(3, 4, Rgba([1, 2, 3, 4]));
+        font.pages[0].image.save(dir.join("test_0.png")).unwrap();
+        std::fs::write(dir.join("test.fnt"), text::to_string(&font)).unwrap();
+
+        let loaded = load_font_file(&dir.join("test.fnt"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(Ok(font), loaded);
+    }
+
+    #[test]
+    fn page_names_have_the_same_length() {
+        let chars = (0..11).map(|i| char_img(65 + i, 10, 10)).collect();
+        let pages = layout_chars("test", chars, layout(10), 10).unwrap();
+
+        assert_eq!(11, pages.len());
+        assert_eq!("test_00.png", pages[0].file);
+        assert_eq!("test_10.png", pages[10].file);
+
+        let pages = layout_chars("test", vec![char_img(65, 10, 10)], layout(10), 10).unwrap();
+        assert_eq!("test_0.png", pages[0].file);
+    }
+
+    #[test]
+    fn glyph_larger_than_page() {
+        let err = layout_chars("test", vec![char_img(66, 10, 10), char_img(65, 30, 10)], layout(16), 10).unwrap_err();
+        assert_eq!("char 65 does not fit a 16x16 page", err);
+
+        let auto = LayoutInfo { atlas: AtlasSize::Auto, max_atlas_size: 16, ..layout(0) };
+        let err = layout_chars("test", vec![char_img(65, 30, 10)], auto, 10).unwrap_err();
+        assert_eq!("char 65 does not fit a 16x16 page", err);
+    }
+}
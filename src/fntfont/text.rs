@@ -0,0 +1,168 @@
+//! Text BMFont format, one line per block or char with key=value pairs
+
+use super::*;
+
+
+pub fn to_string(font: &FontInfo) -> String {
+    info_string(font) + &common_line_string(font) + &distance_field_string(font) + &pages_string(&font.pages, &font.kernings)
+}
+
+
+fn info_string(font: &FontInfo) -> String {
+    let mut res = "info ".to_string();
+
+    res += &format!("face=\"{}\" size={} bold={} italic={} charset=\"{}\" unicode={} stretchH={} smooth={} aa={} ",
+                    replace_quotes(&font.face), font.size, font.bold as i32, font.italic as i32, replace_quotes(&font.charset), font.unicode, font.stretch_h, font.smooth, font.aa);
+
+    let p = font.padding;
+    res += &format!("padding={},{},{},{} ", p[0], p[1], p[2], p[3]);
+
+    res += &format!("spacing={},{}\n", font.spacing[0], font.spacing[1]);
+
+    res
+}
+
+
+pub(super) fn common_line_string(font: &FontInfo) -> String {
+    let mut res = "common ".to_string();
+
+    res += &format!("lineHeight={:?} base={} scaleW={} scaleH={} pages={} packed={}\n",
+                    font.line_height, font.base, font.scale_w, font.scale_h, font.pages.len(), font.packed);
+    res
+}
+
+
+/// Field type and range for distance fields, so shaders know how to decode the texture.
+/// Same line as msdf-bmfont writes, empty for raster fonts
+fn distance_field_string(font: &FontInfo) -> String {
+    match &font.distance_field {
+        Some(df) => format!("distanceField fieldType={} distanceRange={}\n", df.field_type, df.distance_range),
+        None => "".to_string()
+    }
+}
+
+
+/// All page lines first, then the chars of all pages and the kernings, like BMFont writes them
+pub(super) fn pages_string(pages: &[Page], kernings: &[KerningInfo]) -> String {
+
+    let mut res = "".to_string();
+    for p in pages {
+        res += &format!("page id={} file=\"{}\"\n", p.id, replace_quotes(&p.file));
+    }
+
+    let chars : Vec::<&FontCharInfo> = pages.iter().flat_map(|p| &p.chars).collect();
+    res += &chars_string(&chars);
+
+    res += &kernings_string(kernings);
+
+    res
+}
+
+fn chars_string(chars: &[&FontCharInfo]) -> String {
+
+    let mut res = format!("chars count={}\n", chars.len());
+
+    for c in chars {
+        res += &format!("char id={}    x={}  y={}  width={}  height={}  xoffset={}  yoffset={}  xadvance={} page={} chnl={}\n", c.id, c.x, c.y, c.width, c.height, c.xoffset, c.yoffset, c.xadvance, c.page, c.chnl);
+    }
+
+    res
+}
+
+pub(super) fn kernings_string(kernings: &[KerningInfo]) -> String {
+
+    let mut res = format!("kernings count={}\n", kernings.len());
+
+    for k in kernings {
+        res += &format!("kerning first={}  second={}  amount={}\n", k.first, k.second, k.amount);
+    }
+
+    res
+}
+
+
+pub fn parse(s: &str) -> Result<FontInfo, String> {
+    let tags = s.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_line)
+        .collect::<Result<Vec::<Tag>, String>>()?;
+
+    from_tags(&tags)
+}
+
+
+/// Block name and key=value pairs of a line. Values can be quoted, a quoted value ends at the next quote
+fn parse_line(line: &str) -> Result<Tag, String> {
+    let mut chars = line.trim().chars().peekable();
+
+    let name : String = chars.by_ref().take_while(|c| !c.is_whitespace()).collect();
+    let mut attrs = HashMap::new();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        // also consumes the '='
+        let key : String = chars.by_ref().take_while(|&c| c != '=').collect();
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return Err(format!("missing closing quote in '{}'", line))
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                value.push(c);
+            }
+        }
+
+        attrs.insert(key, value);
+    }
+
+    Ok((name, attrs))
+}
+
+
+/// The format has no escapes, so a quote inside a quoted value is replaced by an apostrophe
+fn replace_quotes(s: &str) -> String {
+    s.replace('"', "'")
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn block_lines() {
+        let fnt = to_string(&sample_font_info());
+        let lines : Vec::<&str> = fnt.lines().collect();
+
+        assert_eq!("info face=\"Test\" size=32 bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=1 aa=1 padding=4,4,4,4 spacing=1,1", lines[0]);
+        assert_eq!("common lineHeight=39 base=30 scaleW=64 scaleH=32 pages=1 packed=0", lines[1]);
+        assert_eq!("distanceField fieldType=sdf distanceRange=16", lines[2]);
+        assert_eq!("page id=0 file=\"test_0.png\"", lines[3]);
+        assert_eq!("chars count=2", lines[4]);
+        assert_eq!("char id=86    x=20  y=2  width=10  height=12  xoffset=-1  yoffset=3  xadvance=9 page=0 chnl=0", lines[6]);
+        assert_eq!("kernings count=1", lines[7]);
+        assert_eq!("kerning first=65  second=86  amount=-2", lines[8]);
+        assert_eq!(9, lines.len());
+    }
+
+    #[test]
+    fn quotes_in_values() {
+        let mut font = sample_font_info();
+        font.face = "My \"Font\" C:\\".to_string();
+
+        let fnt = to_string(&font);
+        assert!(fnt.starts_with("info face=\"My 'Font' C:\\\" size=32"));
+        assert_eq!("My 'Font' C:\\", parse(&fnt).unwrap().face);
+    }
+}
@@ -0,0 +1,181 @@
+//! XML BMFont format, same blocks as the text format as elements with attributes
+
+use super::*;
+
+
+pub fn to_string(font: &FontInfo) -> String {
+    let mut res = "<?xml version=\"1.0\"?>\n<font>\n".to_string();
+
+    let p = font.padding;
+    res += &format!("  <info face=\"{}\" size=\"{}\" bold=\"{}\" italic=\"{}\" charset=\"{}\" unicode=\"{}\" stretchH=\"{}\" smooth=\"{}\" aa=\"{}\" padding=\"{},{},{},{}\" spacing=\"{},{}\"/>\n",
+                    escape(&font.face), font.size, font.bold as i32, font.italic as i32, escape(&font.charset), font.unicode, font.stretch_h, font.smooth, font.aa,
+                    p[0], p[1], p[2], p[3], font.spacing[0], font.spacing[1]);
+
+    res += &format!("  <common lineHeight=\"{}\" base=\"{}\" scaleW=\"{}\" scaleH=\"{}\" pages=\"{}\" packed=\"{}\"/>\n",
+                    font.line_height, font.base, font.scale_w, font.scale_h, font.pages.len(), font.packed);
+
+    if let Some(df) = &font.distance_field {
+        res += &format!("  <distanceField fieldType=\"{}\" distanceRange=\"{}\"/>\n", escape(&df.field_type), df.distance_range);
+    }
+
+    res += "  <pages>\n";
+    for p in &font.pages {
+        res += &format!("    <page id=\"{}\" file=\"{}\"/>\n", p.id, escape(&p.file));
+    }
+    res += "  </pages>\n";
+
+    let chars : Vec::<&FontCharInfo> = font.pages.iter().flat_map(|p| &p.chars).collect();
+    res += &format!("  <chars count=\"{}\">\n", chars.len());
+    for c in chars {
+        res += &format!("    <char id=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" xoffset=\"{}\" yoffset=\"{}\" xadvance=\"{}\" page=\"{}\" chnl=\"{}\"/>\n",
+                        c.id, c.x, c.y, c.width, c.height, c.xoffset, c.yoffset, c.xadvance, c.page, c.chnl);
+    }
+    res += "  </chars>\n";
+
+    res += &format!("  <kernings count=\"{}\">\n", font.kernings.len());
+    for k in &font.kernings {
+        res += &format!("    <kerning first=\"{}\" second=\"{}\" amount=\"{}\"/>\n", k.first, k.second, k.amount);
+    }
+    res += "  </kernings>\n";
+
+    res += "</font>\n";
+    res
+}
+
+
+/// Elements are read as tags with their attributes, nesting and text content are ignored
+pub fn parse(s: &str) -> Result<FontInfo, String> {
+    let mut tags = vec![];
+    let mut rest = s;
+
+    while let Some(start) = rest.find('<') {
+        let end = element_end(&rest[start..]).ok_or("missing '>' at the end of an element")? + start;
+        let element = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        // declarations, comments and closing tags
+        if element.starts_with(['?', '!', '/']) {
+            continue;
+        }
+
+        tags.push(parse_element(element.trim_end_matches('/'))?);
+    }
+
+    from_tags(&tags)
+}
+
+
+/// Index of the '>' closing the element, a '>' in a quoted attribute value does not end it
+fn element_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '>') => return Some(i),
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+
+fn parse_element(element: &str) -> Result<Tag, String> {
+    let element = element.trim();
+    let (name, mut rest) = element.split_once(char::is_whitespace).unwrap_or((element, ""));
+    let mut attrs = HashMap::new();
+
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+
+        let err = || format!("invalid attribute in <{}>", name);
+
+        let (key, value) = rest.split_once('=').ok_or_else(err)?;
+        let value = value.trim_start();
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'').ok_or_else(err)?;
+        let close = value[1..].find(quote).ok_or_else(err)? + 1;
+
+        attrs.insert(key.trim().to_string(), unescape(&value[1..close]));
+        rest = &value[close + 1..];
+    }
+
+    Ok((name.to_string(), attrs))
+}
+
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+
+/// Escape the characters that can not appear in an attribute value
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn elements() {
+        let xml = to_string(&sample_font_info());
+        let lines : Vec::<&str> = xml.lines().collect();
+
+        assert_eq!("<?xml version=\"1.0\"?>", lines[0]);
+        assert_eq!("  <info face=\"Test\" size=\"32\" bold=\"0\" italic=\"0\" charset=\"\" unicode=\"1\" stretchH=\"100\" smooth=\"1\" aa=\"1\" padding=\"4,4,4,4\" spacing=\"1,1\"/>", lines[2]);
+        assert_eq!("  <common lineHeight=\"39\" base=\"30\" scaleW=\"64\" scaleH=\"32\" pages=\"1\" packed=\"0\"/>", lines[3]);
+        assert_eq!("  <distanceField fieldType=\"sdf\" distanceRange=\"16\"/>", lines[4]);
+        assert_eq!("    <page id=\"0\" file=\"test_0.png\"/>", lines[6]);
+        assert_eq!("  <chars count=\"2\">", lines[8]);
+        assert_eq!("    <char id=\"86\" x=\"20\" y=\"2\" width=\"10\" height=\"12\" xoffset=\"-1\" yoffset=\"3\" xadvance=\"9\" page=\"0\" chnl=\"0\"/>", lines[10]);
+        assert_eq!("    <kerning first=\"65\" second=\"86\" amount=\"-2\"/>", lines[13]);
+        assert_eq!("</font>", lines[15]);
+    }
+
+    #[test]
+    fn parse_single_quotes_and_entities() {
+        let xml = "<?xml version=\"1.0\"?>
+<!-- made by hand -->
+<font>
+  <info face='A &amp; B' size = \"16\"/>
+  <pages><page id=\"0\" file=\"a.png\" /></pages>
+</font>";
+        let font = parse(xml).unwrap();
+
+        assert_eq!("A & B", font.face);
+        assert_eq!(16, font.size);
+        assert_eq!("a.png", font.pages[0].file);
+    }
+
+    #[test]
+    fn greater_than_in_value() {
+        let mut font = sample_font_info();
+        font.face = "a>b".to_string();
+        font.pages[0].image = ImageBuffer::new(0, 0);
+
+        assert_eq!(Ok(font.clone()), parse(&to_string(&font)));
+
+        // unescaped, which is valid in an attribute value
+        let xml = "<font><info face=\"a>b\" size='16'/></font>";
+        assert_eq!("a>b", parse(xml).unwrap().face);
+        assert!(parse("<font><info face=\"a>b/></font>").is_err());
+    }
+
+    #[test]
+    fn escape_attribute() {
+        assert_eq!("A &amp; &quot;B&quot; &lt;C&gt;", escape("A & \"B\" <C>"));
+    }
+}
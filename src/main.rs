@@ -1,321 +1,486 @@
-use std::fmt::Write;
-use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use freetype::{Library, face::LoadFlag};
 use image::{ImageBuffer, RgbaImage, Rgba};
 use crate::fntfont::*;
+use crate::cli::Args;
+use crate::pack::{AtlasSize, PackHeuristic, SortOrder};
 mod fntfont;
+mod cli;
+mod sdf;
+mod shape;
+mod msdf;
+mod pack;
+mod charset;
+mod ttf;
 
 // https://freetype.org/freetype2/docs/glyphs/glyphs-3.html
 
 
+/// Padding and spread are in output pixels
 #[derive(Debug, Clone, Copy)]
 pub struct GenInfo {
-    upscale_res: u32,
+    size: u32,
+    // bitmaps are rendered at size * upscale, and downsampled to size after the distances are computed
+    upscale: u32,
     padding: u32,
-    spread: u32
+    spread: u32,
+    mode: RenderMode,
+    edge: EdgeMode
 }
 
+impl GenInfo {
 
+    /// Pixel size the bitmaps are rendered at
+    pub fn upscale_res(&self) -> u32 {
+        self.size * self.upscale
+    }
 
-fn main() {
-
-    let args = std::env::args();
-
-    let size : u32 = std::env::args().nth(1).expect("no size given").parse().unwrap();
+    /// Padding actually added around each glyph image. Raster glyphs are written without padding
+    pub fn glyph_padding(&self) -> u32 {
+        match self.mode {
+            RenderMode::Raster => 0,
+            RenderMode::Sdf | RenderMode::VectorSdf | RenderMode::Msdf | RenderMode::Mtsdf => self.padding
+        }
+    }
 
-    let lib = Library::init().unwrap();
-    // TODO: take path as arg param
-    let face = lib.new_face("E:/repos/rust-sdf-tool/test_fonts/consolas.ttf", 0).unwrap();
+    /// Total range of distances encoded in 0.0..1.0, in pixels
+    pub fn distance_range(&self) -> u32 {
+        self.spread * 2
+    }
+}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Plain anti aliased coverage in the alpha channel
+    Raster,
+    /// Signed distance field in the alpha channel, 0.5 is the edge
+    Sdf,
+    /// Like Sdf, but the distances are computed from the glyph outline instead of a rendered bitmap
+    VectorSdf,
+    /// Multi channel signed distance field from the glyph outline in rgb, the median of the channels is the distance
+    Msdf,
+    /// Msdf in rgb and the true signed distance in alpha, for effects that need the distance far from the edge
+    Mtsdf,
+}
 
-    //let img = create_raster_img_with_baseline('\u{61}', &face, 12);
-    //img.save("A.png");
+impl RenderMode {
 
-    let upscale_res = size;
-    let gen_info = GenInfo {
-        upscale_res,
-        padding: (0.125 * upscale_res as f64) as u32,// this will result in padding of 4 px in 32 px font size on each side
-        spread: upscale_res / 4
-    };
+    /// Name of the field type for the distance field modes, None for raster
+    pub fn field_type(&self) -> Option<&'static str> {
+        match self {
+            RenderMode::Raster => None,
+            RenderMode::Sdf | RenderMode::VectorSdf => Some("sdf"),
+            RenderMode::Msdf => Some("msdf"),
+            RenderMode::Mtsdf => Some("mtsdf"),
+        }
+    }
+}
 
+/// How the edge is found in the rendered bitmap when computing distances
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// Every pixel with coverage is inside, edges are on pixel borders
+    Pixel,
+    /// Use the anti aliased coverage to place the edge inside the pixel
+    Coverage,
+}
 
-    let mut chars : Vec::<(CharInfo,RgbaImage)> = vec![];
+impl std::str::FromStr for EdgeMode {
+    type Err = String;
 
-    // generate info for all chars 0..250
-    for chr in 32..255 {
-        let (chr_info, img) = generate_char_info(chr as u32, &face, gen_info);
-        chars.push((chr_info, img));
-        print!("\r{:?}/255",chr);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pixel" => Ok(EdgeMode::Pixel),
+            "coverage" => Ok(EdgeMode::Coverage),
+            _ => Err(format!("unknown edge mode '{}', expected pixel or coverage", s))
+        }
     }
-    println!("");
-
-    let lineheight = face.height() >> 6;
-
-    write_font_files(&face, gen_info, chars);
 }
 
+impl std::str::FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raster" => Ok(RenderMode::Raster),
+            "sdf" => Ok(RenderMode::Sdf),
+            "vector-sdf" => Ok(RenderMode::VectorSdf),
+            "msdf" => Ok(RenderMode::Msdf),
+            "mtsdf" => Ok(RenderMode::Mtsdf),
+            _ => Err(format!("unknown render mode '{}', expected raster, sdf, vector-sdf, msdf or mtsdf", s))
+        }
+    }
+}
 
-fn generate_char_info(chr: u32, face: &freetype::Face, gen_info: GenInfo)  -> (CharInfo, RgbaImage) {
-
-    let mut s = "".to_string();
+/// Where and under what name the generated files are written
+#[derive(Debug, Clone)]
+pub struct OutputInfo {
+    dir: PathBuf,
+    name: String,
+    format: FntFormat,
+}
 
-    //std::io::stdin().read_line(&mut s).expect("Rad line");
+/// How the glyphs are placed on the pages
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutInfo {
+    atlas: AtlasSize,
+    // largest page size tried with auto, and the page size when the glyphs do not fit on one page
+    max_atlas_size: u32,
+    // auto only picks power of two sizes
+    power_of_two: bool,
+    heuristic: PackHeuristic,
+    sort: SortOrder,
+}
 
-    face.set_pixel_sizes(gen_info.upscale_res, gen_info.upscale_res).unwrap();
-    face.load_char(chr as usize, LoadFlag::RENDER).unwrap();
 
-    let padding = gen_info.padding;
-    let spread = gen_info.spread;
+fn main() {
 
-    let glyph = face.glyph();
-    let bitmap = glyph.bitmap();
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{}", cli::USAGE);
+            return;
+        },
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, cli::USAGE);
+            std::process::exit(1);
+        }
+    };
 
-    let g = glyph.get_glyph().unwrap();
+    let lib = Library::init().unwrap();
+    let face = match lib.new_face(&args.font, args.face_index) {
+        Ok(face) => face,
+        Err(err) => {
+            eprintln!("error: could not load font {:?}: {}", args.font, err);
+            std::process::exit(1);
+        }
+    };
 
-    let g_metrics = glyph.metrics();
+    let size = args.size;
+    let gen_info = GenInfo {
+        size,
+        upscale: args.upscale,
+        padding: args.padding.unwrap_or((0.125 * size as f64) as u32),// this will result in padding of 4 px in 32 px font size on each side
+        spread: args.spread.unwrap_or(size / 4),
+        mode: args.mode,
+        edge: args.edge
+    };
 
-    let rows = bitmap.rows() as u32;
-    let width = bitmap.width() as u32;
-    let bitmap_buffer = bitmap.buffer();
+    if gen_info.mode != RenderMode::Raster && gen_info.spread == 0 {
+        eprintln!("error: spread has to be larger than 0 when generating distance fields");
+        std::process::exit(1);
+    }
 
+    let output = OutputInfo {
+        name: args.name.clone().unwrap_or_else(|| format!("{}_{}", face.family_name().unwrap_or_default(), size)),
+        dir: args.out_dir.clone(),
+        format: args.format,
+    };
 
-    // buffer with padding
-    let sdf_h = rows + padding * 2;
-    let sdf_w = width + padding * 2;
+    if let Err(err) = std::fs::create_dir_all(&output.dir) {
+        eprintln!("error: could not create output directory {:?}: {}", output.dir, err);
+        std::process::exit(1);
+    }
 
 
-    let mut sdf_buffer : Vec::<f64> = vec![0.0; (sdf_w * sdf_h)as usize];
+    let jobs = args.jobs.unwrap_or_else(|| std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
 
-    for y in 0..rows {
-        for x in 0..width {
-            let val = bitmap_buffer[(y * width + x) as usize];
-            sdf_buffer[((y + padding) * sdf_w + x +  padding) as usize] = val as f64;
+    let codepoints = match charset::codepoints(&args.charset) {
+        Ok(codepoints) => codepoints,
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            std::process::exit(1);
         }
-    }
+    };
 
+    // freetype-rs has no lookup for variation sequences, they come from the cmap of the font file
+    let variants_font = if codepoints.iter().any(|&c| charset::split_id(c).1.is_some()) {
+        let font = std::fs::read(&args.font).ok().and_then(|data| ttf::parse_font(&data));
+        if font.is_none() {
+            eprintln!("warning: could not read the variation sequences of {:?}", args.font);
+        }
+        font
+    } else {
+        None
+    };
 
-    let mut tmp_img: RgbaImage = ImageBuffer::new(width, rows);
+    let glyphs = codepoints.into_iter().map(|c| match charset::split_id(c) {
+        (base, None) => (c, face.get_char_index(base as usize)),
+        (base, Some(selector)) => (c, variants_font.as_ref().map_or(0, |f| f.cmap_table.get_variant_glyph_id(base, selector) as u32)),
+    });
 
-    for y in 0..tmp_img.height() {
-        for x in 0..tmp_img.width() {
-            let val = bitmap_buffer[(y * width + x) as usize];
-            tmp_img.put_pixel(x,y,  Rgba([val, val, val, val]));
-        }
+    // chars the font does not have would all be the same missing glyph box
+    let (glyphs, missing) : (Vec::<_>, Vec::<_>) = glyphs.partition(|&(_, glyph)| glyph != 0);
+    if !missing.is_empty() {
+        eprintln!("warning: skipping {} chars not in the font", missing.len());
     }
 
-    // with padding for sdf
-    //let mut img: RgbaImage = ImageBuffer::new(width + padding * 2, rows + padding * 2);
+    let chars = generate_chars(&args, gen_info, &glyphs, jobs);
 
-    // for regular font atlas
-    let mut img: RgbaImage = ImageBuffer::new(width, rows);
+    let layout = LayoutInfo {
+        atlas: args.atlas_size,
+        max_atlas_size: args.max_atlas_size,
+        power_of_two: !args.npot,
+        heuristic: args.pack,
+        sort: args.sort,
+    };
 
-    let mut max_d = 0.0;
-    for y in 0..img.height() {
-        for x in 0..img.width() {
-            // sdf
-            /*
+    if let Err(msg) = write_font_files(&face, gen_info, layout, &output, chars) {
+        eprintln!("error: {}", msg);
+        std::process::exit(1);
+    }
+}
 
-            let px_v = 0.0 ;
 
-            let px_v = px_value(bitmap_buffer, width as i32, rows as i32, padding as i32, x as i32, y as i32, spread as i32);
+/// Generate the chars on jobs threads. Each thread loads its own face, since a freetype face can not be shared.
+/// glyphs are the char ids with their glyph index in the font.
+/// The result is in the same order as glyphs, no matter which thread generated what
+fn generate_chars(args: &Args, gen_info: GenInfo, glyphs: &[(u32, u32)], jobs: usize) -> Vec::<(CharInfo, RgbaImage)> {
 
-            let mut val = (px_v * 255.0) as u8;
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let total = glyphs.len();
 
-            let mut c = 0;
-            if val > 0 {
-                c = 255
-            };
+    let mut chars : Vec::<(usize, (CharInfo, RgbaImage))> = std::thread::scope(|scope| {
+        let workers : Vec::<_> = (0..jobs.clamp(1, total.max(1))).map(|_| scope.spawn(|| {
+            let lib = Library::init().unwrap();
+            let face = lib.new_face(&args.font, args.face_index).unwrap();
 
-            //img.put_pixel(x, y, Rgba([c, c, c, val]));
-            */
-            // regular texture
+            let mut res = vec![];
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= total {
+                    break;
+                }
 
-            let v = bitmap_buffer[(y * width + x) as usize];
-            img.put_pixel(x, y, Rgba([0, 0, 0, v]));
-        }
-    }
+                let (chr, glyph_index) = glyphs[i];
+                res.push((i, generate_char_info(chr, glyph_index, &face, gen_info)));
+                let count = done.fetch_add(1, Ordering::Relaxed) + 1;
+                print!("\r{}/{}", count, total);
+            }
+            res
+        })).collect();
 
+        workers.into_iter().flat_map(|w| w.join().unwrap()).collect()
+    });
+    println!();
 
-    (CharInfo {
-        chr: chr as u32,
-        advance_x: glyph.advance().x >> 6,
-        advance_y: 0, // also not used by text renderer. Is used when align horizontal
-        padding_x: gen_info.padding as i32,
-        padding_y: gen_info.padding as i32,
-        offset_x: g_metrics.horiBearingX >> 6,
-        offset_y: g_metrics.horiBearingY >> 6,
-        height: rows,
-        width: width
-    }, img)
+    chars.sort_by_key(|(i, _)| *i);
+    chars.into_iter().map(|(_, c)| c).collect()
 }
 
 
-// find min dist to a border pixel
-// return in range 0.0..1.0 where 0.5 border
-fn px_value(buffer: &[u8], buf_w: i32, buf_h: i32, padding: i32, sdf_x: i32, sdf_y: i32, spread: i32) -> f64 {
-
-    let outside = sdf_x < padding || sdf_x >= buf_w + padding || sdf_y < padding || sdf_y >= buf_h + padding;
+fn generate_char_info(chr: u32, glyph_index: u32, face: &freetype::Face, gen_info: GenInfo)  -> (CharInfo, RgbaImage) {
 
-    // set state (inside or out)
-    let mut state = 0;
-    let state_idx = ((sdf_y - padding ) * buf_w + sdf_x - padding) as usize;
-    if !outside {
-
-        if buffer[state_idx] > 0 {
-            state = 1;
-        };
-        assert!(state == 1 || state == 0);
+    match gen_info.mode {
+        RenderMode::Raster | RenderMode::Sdf => {
+            face.set_pixel_sizes(gen_info.upscale_res(), gen_info.upscale_res()).unwrap();
+            generate_bitmap_char_info(chr, glyph_index, face, gen_info)
+        },
+        RenderMode::VectorSdf | RenderMode::Msdf | RenderMode::Mtsdf => {
+            // distances are exact from the outline, so no upscaling needed
+            face.set_pixel_sizes(gen_info.size, gen_info.size).unwrap();
+            generate_outline_char_info(chr, glyph_index, face, gen_info)
+        }
     }
+}
 
 
-    let x_start = sdf_x - spread;
-    let x_end = sdf_x + spread;
-
-    let y_start = sdf_y - spread;
-    let y_end = sdf_y + spread;
+/// Render the glyph bitmap with freetype and use it directly, or compute the distance field from it.
+/// The bitmap is rendered upscale times larger than the output and downsampled after the distances are computed
+fn generate_bitmap_char_info(chr: u32, glyph_index: u32, face: &freetype::Face, gen_info: GenInfo)  -> (CharInfo, RgbaImage) {
 
-    let mut min_dist_squared = spread * spread;
+    // embedded bitmap strikes are 1 bit per pixel, render the outline to get 8 bit coverage
+    face.load_glyph(glyph_index, LoadFlag::RENDER | LoadFlag::NO_BITMAP).unwrap();
 
-    let max_dist = f64::sqrt(min_dist_squared as f64);
-    // iterate only over pixels in buffer, since all the others outside will be further away
+    let n = gen_info.upscale as i32;
+    let padding = gen_info.glyph_padding();
 
-    // x and y is index into the final sdf image
-    for y in y_start..y_end {
-        for x in x_start..x_end {
+    let glyph = face.glyph();
+    let bitmap = glyph.bitmap();
 
-            // map to x and y in buffer. and if outside skip
-            let buf_x = x - padding;
-            let buf_y = y - padding;
+    let rows = bitmap.rows();
+    let width = bitmap.width();
+    // rows can be padded, the pitch is the number of bytes per row
+    let pitch = bitmap.pitch();
+    let bitmap_buffer = bitmap.buffer();
 
-            if buf_x < 0 || buf_x >= buf_w || buf_y < 0 || buf_y >= buf_h {
-                continue;
-            }
+    // bitmap position in upscaled pixels, y up
+    let left_hi = glyph.bitmap_left();
+    let top_hi = glyph.bitmap_top();
 
-            let buffer_v = buffer[(buf_y * buf_w + buf_x) as usize].max(0).min(1);
+    // output pixel bounds of the glyph, y up
+    let left = left_hi.div_euclid(n);
+    let top = -(-top_hi).div_euclid(n);
+    let right = -(-(left_hi + width)).div_euclid(n);
+    let bottom = (top_hi - rows).div_euclid(n);
 
-            if buffer_v != state {
-                let x_diff = sdf_x - x;
-                let y_diff = sdf_y - y;
+    let out_w = (right - left) as u32 + padding * 2;
+    let out_h = (top - bottom) as u32 + padding * 2;
 
-                let before = min_dist_squared;
-                min_dist_squared = i32::min(min_dist_squared, x_diff * x_diff + y_diff * y_diff);
-
-                if min_dist_squared < before {
-                    //println!("{:?}", (x_diff, y_diff, min_dist_squared));
-                                      //, sdf_y, buffer_v, state, buf_x, buf_y, padding, buffer[(buf_y * buf_w + buf_x) as usize], (buf_y * buf_w + buf_x) as usize));
-                }
-            }
+    // upscaled canvas covering exactly the output pixels, including the padding
+    let canvas_w = out_w * n as u32;
+    let canvas_h = out_h * n as u32;
+    let offset_x = left_hi - (left - padding as i32) * n;
+    let offset_y = (top + padding as i32) * n - top_hi;
 
+    let mut canvas = vec![0; (canvas_w * canvas_h) as usize];
+    for y in 0..rows {
+        for x in 0..width {
+            let canvas_idx = (y + offset_y) as u32 * canvas_w + (x + offset_x) as u32;
+            canvas[canvas_idx as usize] = bitmap_buffer[(y * pitch + x) as usize];
         }
     }
 
-    let min_dist = f64::sqrt(min_dist_squared as f64);
-
-    assert!(min_dist <= max_dist);
-
-    // outside become negative inside positive
-    let mut mul = 1.0;
-    if state == 0 {
-        mul = -1.0
-    }
+    let spread_hi = gen_info.spread * n as u32;
+    let values = match gen_info.mode {
+        RenderMode::Raster => canvas.iter().map(|&v| v as f64 / 255.0).collect(),
+        _ => match gen_info.edge {
+            EdgeMode::Pixel => sdf::distance_field(&canvas, canvas_w, canvas_h, 0, spread_hi),
+            EdgeMode::Coverage => sdf::distance_field_aa(&canvas, canvas_w, canvas_h, 0, spread_hi),
+        }
+    };
 
+    let values = sdf::downsample(&values, canvas_w as usize, canvas_h as usize, n as usize);
 
-    let scaled = (min_dist / max_dist) * mul;
+    let mut img: RgbaImage = ImageBuffer::new(out_w, out_h);
 
-    // map from [-1.0..1.0] to [-0.5..0.5] and + 0.5 to be in [0.0..1.0]
-    let mut res = scaled / 2.0 + 0.5;
-    assert!((state == 1 && res > 0.5) || (state == 0 && res <= 0.5));
-    //println!("{:?}", res);
-    res
+    for y in 0..img.height() {
+        for x in 0..img.width() {
+            let v = values[(y * out_w + x) as usize];
+            if gen_info.mode == RenderMode::Raster {
+                img.put_pixel(x, y, Rgba([0, 0, 0, (v * 255.0).round() as u8]));
+            } else {
+                img.put_pixel(x, y, sdf_pixel(v));
+            }
+        }
+    }
 
+    (CharInfo {
+        chr,
+        glyph_index,
+        advance_x: (glyph.advance().x as f64 / 64.0 / n as f64).round() as i32,
+        advance_y: 0, // also not used by text renderer. Is used when align horizontal
+        padding_x: padding as i32,
+        padding_y: padding as i32,
+        // offsets are for the padded image
+        offset_x: left - padding as i32,
+        offset_y: top + padding as i32,
+        height: img.height(),
+        width: img.width()
+    }, img)
 }
 
 
+/// Compute the distance field from the glyph outline, no bitmap is rendered
+fn generate_outline_char_info(chr: u32, glyph_index: u32, face: &freetype::Face, gen_info: GenInfo)  -> (CharInfo, RgbaImage) {
 
-fn create_raster_img_with_baseline(chr: char, face: &freetype::Face, font_size: u32) -> RgbaImage {
+    // hinting distorts the outline to fit the pixel grid, which is not wanted when it is sampled as a distance field.
+    // Embedded bitmap strikes, like calibri has at small sizes, have no outline
+    face.load_glyph(glyph_index, LoadFlag::NO_HINTING | LoadFlag::NO_BITMAP).unwrap();
 
-    face.set_pixel_sizes(font_size, font_size).unwrap();
-    println!("{:?}", chr);
-    face.load_char(chr as usize, LoadFlag::RENDER).unwrap();
+    let padding = gen_info.glyph_padding();
+    let spread = gen_info.spread;
 
     let glyph = face.glyph();
-
-    let metrics = face.size_metrics().unwrap();
-    let max_descent = metrics.descender >> 6;
-    let max_ascent = metrics.ascender >> 6;
-
-
-    let pixel_h = ( max_ascent + i32::abs(max_descent)) as  u32;
-    let pixel_w =  font_size;
-
-    let bitmap = glyph.bitmap();
-
-    let g = glyph.get_glyph().unwrap();
     let g_metrics = glyph.metrics();
-    println!("{:#?}", g_metrics);
-
-    let rows = bitmap.rows();
-    let width = bitmap.width();
-    let buffer = bitmap.buffer();
-
-    let mut img: RgbaImage = ImageBuffer::new(pixel_w, pixel_h);
 
-    let rows = bitmap.rows();
-    let width = bitmap.width();
-    let buffer = bitmap.buffer();
-
-
-    // >> 6 to divide by 64. since they are in 26.6 fractional format
-
-    let left = g_metrics.horiAdvance >> 6;
-    let g_width = g_metrics.width >> 6;
+    // the contour iterator in freetype-rs reads out of bounds on outlines without contours, like space
+    let shape = match glyph.outline() {
+        Some(outline) if glyph.raw().outline.n_contours > 0 => shape::Shape::from_outline(&outline),
+        _ => shape::Shape::default()
+    };
 
+    // pixel bounds of the glyph, y up
+    let left = (g_metrics.horiBearingX as f64 / 64.0).floor() as i32;
+    let top = (g_metrics.horiBearingY as f64 / 64.0).ceil() as i32;
+    let right = ((g_metrics.horiBearingX + g_metrics.width) as f64 / 64.0).ceil() as i32;
+    let bottom = ((g_metrics.horiBearingY - g_metrics.height) as f64 / 64.0).floor() as i32;
 
-    let advance_x = glyph.advance().x >> 6;
+    let width = (right - left).max(0) as u32;
+    let rows = (top - bottom).max(0) as u32;
 
-    let advance_y = glyph.advance().y >> 6;
+    let msdf = match gen_info.mode {
+        RenderMode::Msdf | RenderMode::Mtsdf => Some(msdf::MsdfShape::new(&shape)),
+        _ => None
+    };
 
-    let x_offset = (pixel_w as i32 - advance_x) / 2;
+    let mut img: RgbaImage = ImageBuffer::new(width + padding * 2, rows + padding * 2);
 
+    for y in 0..img.height() {
+        for x in 0..img.width() {
+            // pixel center in outline coordinates
+            let p = shape::Vec2::new(
+                (left - padding as i32 + x as i32) as f64 + 0.5,
+                (top + padding as i32 - y as i32) as f64 - 0.5
+            );
+
+            let dist = if shape.contours.is_empty() {
+                -(spread as f64)
+            } else {
+                shape.signed_distance(p)
+            };
 
-    let bearing_y = g_metrics.horiBearingY >> 6;
-    let y_offset =  (pixel_h as i32 - bearing_y) /2;
+            let px = match &msdf {
+                None => sdf_pixel(sdf::encode(dist, spread)),
+                Some(msdf) => {
+                    let mut d = msdf.distances(p);
 
+                    // where edges from different contours clash the median can end up on the wrong side,
+                    // use the true distance for those pixels
+                    if (msdf::median(d) > 0.0) != (dist > 0.0) {
+                        d = [dist; 3];
+                    }
 
+                    let [r, g, b] = d.map(|d| (sdf::encode(d, spread) * 255.0).round() as u8);
 
-    // Draw baseline, relative to char
-    let baseline_h = (y_offset + bearing_y) as u32;
+                    let a = match gen_info.mode {
+                        RenderMode::Mtsdf => (sdf::encode(dist, spread) * 255.0).round() as u8,
+                        _ => 255
+                    };
 
-    println!("baseline: {:?}", (baseline_h));
+                    Rgba([r, g, b, a])
+                }
+            };
 
-    for y in 0..pixel_h {
-        for x in 0..pixel_w {
-            if y == baseline_h {
-                img.put_pixel(x as u32, y as u32, Rgba([255, 0, 255, 255]));
-            }
+            img.put_pixel(x, y, px);
         }
     }
 
-    for y in 0..rows {
-        for x in 0..width {
+    (CharInfo {
+        chr,
+        glyph_index,
+        advance_x: (glyph.advance().x as f64 / 64.0).round() as i32,
+        advance_y: 0,
+        padding_x: padding as i32,
+        padding_y: padding as i32,
+        offset_x: left - padding as i32,
+        offset_y: top + padding as i32,
+        height: img.height(),
+        width: img.width()
+    }, img)
+}
 
-            let val = buffer[(y * width + x) as usize];
-            let y_index = y_offset + y as i32;
-            let x_index = x_offset + x;
 
-            if val > 0 {
-                img.put_pixel(x_index as u32, y_index as u32, Rgba([0, 0, 0, val]));
+/// Distance field value in 0.0..1.0 to pixel, the distance is stored in alpha
+fn sdf_pixel(px_v: f64) -> Rgba<u8> {
+    let val = (px_v * 255.0) as u8;
 
-            }
-        }
-    }
+    let mut c = 0;
+    if val > 0 {
+        c = 255
+    };
 
-    img
+    Rgba([c, c, c, val])
 }
 
-#[derive(Clone, Debug)]
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct CharInfo {
     chr: u32,
+    // the glyph in the font, variation sequences have a different glyph than the char id's codepoint
+    glyph_index: u32,
     width: u32,
     height: u32,
     advance_x: i32,
@@ -327,37 +492,99 @@ pub struct CharInfo {
 }
 
 
-
-
 #[cfg(test)]
 mod test {
 
     use super::*;
 
-    #[test]
-    fn test_px_value() {
-
-        let mut buffer = [1; 8*8];
-
-        for i in 0..8 {
-            buffer[i] = 0;
-            buffer[i+8] = 0;
-        }
+    fn gen_info(size: u32, mode: RenderMode) -> GenInfo {
+        GenInfo { size, upscale: 1, padding: 2, spread: 4, mode, edge: EdgeMode::Coverage }
+    }
 
-        let padding = 4;
-        let spread = 8;
+    fn calibri(lib: &Library) -> freetype::Face {
+        lib.new_face(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf"), 0).unwrap()
+    }
 
-        let x = 4;
-        let y = 4;
-        let v = px_value(&buffer, 8, 8, padding, padding + x, padding + y, spread);
+    #[test]
+    fn render_at_bitmap_strike_size() {
+        let lib = Library::init().unwrap();
+        let face = calibri(&lib);
 
-        println!("{:?}", v);
+        for mode in [RenderMode::Raster, RenderMode::Sdf] {
+            for c in ['A', 'W', 'g', '1'] {
+                let (info, img) = generate_char_info(c as u32, face.get_char_index(c as usize), &face, gen_info(16, mode));
 
+                assert!(info.width > 0 && info.height > 0);
+                // anti aliased coverage, a 1 bit strike would only have 0 and 255
+                assert!(img.pixels().any(|p| p.0[3] > 0 && p.0[3] < 255), "{:?} {}", mode, c);
+            }
+        }
+    }
 
-        assert!(true);
+    #[test]
+    fn outline_at_bitmap_strike_size() {
+        // calibri has embedded bitmaps at 16px, which have no outline
+        let lib = Library::init().unwrap();
+        let face = calibri(&lib);
+        let glyph_index = face.get_char_index('A' as usize);
 
+        let (info, img) = generate_char_info('A' as u32, glyph_index, &face, gen_info(16, RenderMode::VectorSdf));
 
+        assert_eq!(9, info.advance_x);
+        // an empty shape would be all outside
+        assert!(img.pixels().any(|p| p.0[3] > 128));
+    }
 
+    #[test]
+    fn same_chars_with_one_and_more_jobs() {
+        let font = concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf");
+        let args = Args::parse(["--font", font, "--size", "24"].iter().map(|s| s.to_string())).unwrap().unwrap();
+
+        let lib = Library::init().unwrap();
+        let face = calibri(&lib);
+        let glyphs : Vec::<(u32, u32)> = "Hello, World! g@0".chars().map(|c| (c as u32, face.get_char_index(c as usize))).collect();
+
+        for mode in [RenderMode::Sdf, RenderMode::Msdf] {
+            let single = generate_chars(&args, gen_info(24, mode), &glyphs, 1);
+            let parallel = generate_chars(&args, gen_info(24, mode), &glyphs, 4);
+
+            assert_eq!(glyphs.len(), parallel.len());
+            for ((info, img), (parallel_info, parallel_img)) in single.iter().zip(&parallel) {
+                assert_eq!(info, parallel_info);
+                assert!(img == parallel_img, "{:?} {}", mode, info.chr);
+            }
+        }
     }
 
+    #[test]
+    fn mtsdf_alpha_is_true_distance() {
+        let lib = Library::init().unwrap();
+        let face = calibri(&lib);
+
+        for c in ['A', 'O', 'g'] {
+            let glyph_index = face.get_char_index(c as usize);
+            let gen_info = gen_info(32, RenderMode::Mtsdf);
+            let (info, img) = generate_char_info(c as u32, glyph_index, &face, gen_info);
+
+            // the outline is loaded at 32px by generate_char_info
+            let shape = shape::Shape::from_outline(&face.glyph().outline().unwrap());
+
+            let mut near_edge = 0;
+            for (x, y, px) in img.enumerate_pixels() {
+                let p = shape::Vec2::new((info.offset_x + x as i32) as f64 + 0.5, (info.offset_y - y as i32) as f64 - 0.5);
+                let dist = shape.signed_distance(p);
+
+                let [r, g, b, a] = px.0;
+                assert_eq!((sdf::encode(dist, gen_info.spread) * 255.0).round() as u8, a, "{} {} {}", c, x, y);
+
+                // within half a pixel of the edge the median of the channels is the distance too
+                if dist.abs() < 0.5 {
+                    let median = msdf::median([r, g, b].map(|v| v as f64));
+                    assert!((median - a as f64).abs() <= 4.0, "{} {} {} median {} alpha {}", c, x, y, median, a);
+                    near_edge += 1;
+                }
+            }
+            assert!(near_edge > 0);
+        }
+    }
 }
@@ -0,0 +1,214 @@
+//! Multi channel signed distance fields
+//! Edges of the outline are assigned colors so every corner is between edges with different colors,
+//! each channel is then the pseudo distance to the closest edge with that channel.
+//! The median of the three channels reconstructs the sharp corners.
+//! Chlumsky, "Shape Decomposition for Multi-channel Distance Fields"
+
+use crate::shape::*;
+
+pub const RED: u8 = 1;
+pub const GREEN: u8 = 2;
+pub const BLUE: u8 = 4;
+pub const YELLOW: u8 = RED | GREEN;
+pub const MAGENTA: u8 = RED | BLUE;
+pub const CYAN: u8 = GREEN | BLUE;
+pub const WHITE: u8 = RED | GREEN | BLUE;
+
+const CHANNELS: [u8; 3] = [RED, GREEN, BLUE];
+
+// direction changes of more than pi - 3.0 radians, about 8 degrees, count as corners. Same default as msdfgen
+const CORNER_ANGLE_THRESHOLD: f64 = 3.0;
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColoredEdge {
+    pub segment: Segment,
+    pub color: u8,
+}
+
+
+#[derive(Debug, Clone)]
+pub struct MsdfShape {
+    pub edges: Vec::<ColoredEdge>,
+    // 1.0 or -1.0, so inside is positive regardless of the contour direction in the font
+    orientation: f64,
+}
+
+impl MsdfShape {
+
+    pub fn new(shape: &Shape) -> Self {
+        let edges = shape.contours.iter().flat_map(color_contour).collect();
+
+        MsdfShape {
+            edges,
+            orientation: shape.orientation(),
+        }
+    }
+
+    /// Signed pseudo distance for each of the red, green and blue channels, positive inside
+    pub fn distances(&self, p: Vec2) -> [f64; 3] {
+
+        let mut closest = [(SignedDistance::INFINITE, None::<&ColoredEdge>, 0.0); 3];
+
+        for edge in &self.edges {
+            let (dist, t) = edge.segment.signed_distance(p);
+
+            for (i, channel) in CHANNELS.iter().enumerate() {
+                if edge.color & channel != 0 && dist.closer_than(&closest[i].0) {
+                    closest[i] = (dist, Some(edge), t);
+                }
+            }
+        }
+
+        let mut res = [f64::NEG_INFINITY; 3];
+        for (i, (dist, edge, t)) in closest.iter().enumerate() {
+            if let Some(edge) = edge {
+                res[i] = self.orientation * edge.segment.pseudo_distance(p, *dist, *t);
+            }
+        }
+        res
+    }
+}
+
+
+pub fn median(v: [f64; 3]) -> f64 {
+    f64::max(f64::min(v[0], v[1]), f64::min(f64::max(v[0], v[1]), v[2]))
+}
+
+
+fn is_corner(a: Vec2, b: Vec2) -> bool {
+    let (a, b) = (a.normalize(), b.normalize());
+    a.dot(b) <= 0.0 || a.cross(b).abs() > CORNER_ANGLE_THRESHOLD.sin()
+}
+
+
+/// Next two channel color, that is not the current or the banned color
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let order = [CYAN, MAGENTA, YELLOW];
+    let start = order.iter().position(|&c| c == color).map(|i| i + 1).unwrap_or(0);
+
+    (0..3).map(|k| order[(start + k) % 3])
+        .find(|&c| c != color && c != banned)
+        .unwrap()
+}
+
+
+/// Spread the positions 0..n over -1, 0 and 1 symmetrically
+fn symmetrical_trichotomy(position: usize, n: usize) -> i32 {
+    (3.0 + 2.875 * position as f64 / (n as f64 - 1.0) - 1.4375 + 0.5) as i32 - 3
+}
+
+
+/// Assign colors to the segments of a contour, so that the edges meeting at a corner never share two channels
+fn color_contour(contour: &Contour) -> Vec::<ColoredEdge> {
+
+    let segments = &contour.segments;
+    let m = segments.len();
+
+    let corners : Vec::<usize> = (0..m).filter(|&i| {
+        let prev = &segments[(i + m - 1) % m];
+        is_corner(prev.tangent(1.0), segments[i].tangent(0.0))
+    }).collect();
+
+    let color = |segment: &Segment, color: u8| ColoredEdge { segment: *segment, color };
+
+    if corners.is_empty() {
+        // smooth contour, all channels are the same
+        let c = switch_color(WHITE, 0);
+        return segments.iter().map(|s| color(s, c)).collect();
+    }
+
+    if corners.len() == 1 {
+        // teardrop, split the contour in three parts starting from the corner
+        let colors = [switch_color(WHITE, 0), WHITE, switch_color(switch_color(WHITE, 0), 0)];
+        let corner = corners[0];
+
+        let mut parts : Vec::<Segment> = (0..m).map(|i| segments[(corner + i) % m]).collect();
+
+        // need at least three edges to get three parts
+        if m < 3 {
+            parts = parts.iter().flat_map(|s| s.split_in_thirds()).collect();
+        }
+
+        let n = parts.len();
+        return parts.iter().enumerate()
+            .map(|(i, s)| color(s, colors[(1 + symmetrical_trichotomy(i, n)) as usize]))
+            .collect();
+    }
+
+    // switch color at every corner, making sure the last spline does not get the color of the first
+    let mut colors = vec![0; m];
+    let mut spline = 0;
+    let start = corners[0];
+    let mut c = switch_color(WHITE, 0);
+    let initial = c;
+
+    for i in 0..m {
+        let index = (start + i) % m;
+        if spline + 1 < corners.len() && corners[spline + 1] == index {
+            spline += 1;
+            let banned = if spline == corners.len() - 1 { initial } else { 0 };
+            c = switch_color(c, banned);
+        }
+        colors[index] = c;
+    }
+
+    segments.iter().zip(colors).map(|(s, c)| color(s, c)).collect()
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn corners_get_different_colors() {
+        let contour = polygon(&[v(0.0, 0.0), v(4.0, 0.0), v(4.0, 4.0), v(2.0, 6.0), v(0.0, 4.0)]);
+        let edges = color_contour(&contour);
+
+        assert_eq!(5, edges.len());
+        for i in 0..edges.len() {
+            let a = edges[i].color;
+            let b = edges[(i + 1) % edges.len()].color;
+            // only one channel in common, so the median changes at the corner
+            assert_eq!(1, (a & b).count_ones(), "edge {} {:b} {:b}", i, a, b);
+        }
+    }
+
+    #[test]
+    fn teardrop_is_split() {
+        // single cubic loop, with one corner where it starts and ends
+        let contour = Contour { segments: vec![
+            Segment::Cubic(v(0.0, 0.0), v(10.0, 10.0), v(-10.0, 10.0), v(0.0, 0.0)),
+        ]};
+        let edges = color_contour(&contour);
+
+        assert_eq!(3, edges.len());
+        assert_eq!(WHITE, edges[1].color);
+        assert_eq!(1, (edges[0].color & edges[2].color).count_ones());
+    }
+
+    #[test]
+    fn median_of_three() {
+        assert_eq!(2.0, median([1.0, 2.0, 3.0]));
+        assert_eq!(2.0, median([3.0, 1.0, 2.0]));
+        assert_eq!(1.0, median([f64::NEG_INFINITY, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn square_median_is_signed_distance() {
+        // clockwise like TrueType, inside should still be positive
+        let shape = Shape { contours: vec![polygon(&[v(0.0, 0.0), v(0.0, 4.0), v(4.0, 4.0), v(4.0, 0.0)])] };
+        let msdf = MsdfShape::new(&shape);
+
+        for p in [v(2.0, 2.0), v(1.0, 3.0), v(-1.0, 2.0), v(2.0, 5.5), v(3.5, 0.2)] {
+            let d = median(msdf.distances(p));
+            assert!((d - shape.signed_distance(p)).abs() < 1e-9, "{:?} {} {}", p, d, shape.signed_distance(p));
+        }
+
+        // outside a corner the median is the distance to the extended edges, keeping the corner sharp
+        let d = median(msdf.distances(v(5.0, 5.0)));
+        assert!((d + 1.0).abs() < 1e-9, "{}", d);
+    }
+}
@@ -0,0 +1,372 @@
+//! Rectangle packing for the glyph images on the atlas pages.
+//! MaxRects keeps a list of all maximal free rectangles, every placement is scored against each of them.
+//! Jukka Jylänki, "A Thousand Ways to Pack the Bin"
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+
+    fn right(&self) -> u32 {
+        self.x + self.w
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.h
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() && other.y < self.bottom()
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        self.x <= other.x && self.y <= other.y && other.right() <= self.right() && other.bottom() <= self.bottom()
+    }
+}
+
+
+/// How the free rectangle for the next placement is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackHeuristic {
+    /// Smallest leftover on the shorter side of the free rectangle
+    BestShortSideFit,
+    /// Smallest leftover on the longer side of the free rectangle
+    BestLongSideFit,
+    /// Smallest free rectangle that fits
+    BestAreaFit,
+    /// Topmost position, then leftmost. Tetris like
+    BottomLeft,
+}
+
+impl std::str::FromStr for PackHeuristic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "short-side" => Ok(PackHeuristic::BestShortSideFit),
+            "long-side" => Ok(PackHeuristic::BestLongSideFit),
+            "area" => Ok(PackHeuristic::BestAreaFit),
+            "bottom-left" => Ok(PackHeuristic::BottomLeft),
+            _ => Err(format!("unknown pack heuristic '{}', expected short-side, long-side, area or bottom-left", s))
+        }
+    }
+}
+
+
+/// Order the glyphs are packed in. Big glyphs first leaves the small ones to fill the gaps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Height,
+    Area,
+}
+
+impl SortOrder {
+
+    /// Indices of sizes in packing order. Ties keep the original order, so the layout is deterministic
+    pub fn order(&self, sizes: &[(u32, u32)]) -> Vec::<usize> {
+        let mut res : Vec::<usize> = (0..sizes.len()).collect();
+        match self {
+            SortOrder::Height => res.sort_by_key(|&i| std::cmp::Reverse((sizes[i].1, sizes[i].0))),
+            SortOrder::Area => res.sort_by_key(|&i| std::cmp::Reverse((sizes[i].0 * sizes[i].1, sizes[i].1))),
+        }
+        res
+    }
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "height" => Ok(SortOrder::Height),
+            "area" => Ok(SortOrder::Area),
+            _ => Err(format!("unknown sort order '{}', expected height or area", s))
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct MaxRectsPacker {
+    heuristic: PackHeuristic,
+    free: Vec::<Rect>,
+}
+
+impl MaxRectsPacker {
+
+    pub fn new(width: u32, height: u32, heuristic: PackHeuristic) -> Self {
+        MaxRectsPacker {
+            heuristic,
+            free: vec![Rect { x: 0, y: 0, w: width, h: height }],
+        }
+    }
+
+    /// Place a w * h rectangle, None when there is no room left for it
+    pub fn insert(&mut self, w: u32, h: u32) -> Option<Rect> {
+
+        let placed = self.free.iter()
+            .filter(|f| w <= f.w && h <= f.h)
+            .map(|f| (self.score(f, w, h), Rect { x: f.x, y: f.y, w, h }))
+            .min_by_key(|(score, _)| *score)
+            .map(|(_, rect)| rect)?;
+
+        self.split_free(&placed);
+        self.prune_free();
+
+        Some(placed)
+    }
+
+    /// Lower is better, the second value breaks ties
+    fn score(&self, free: &Rect, w: u32, h: u32) -> (u64, u64) {
+        let leftover_w = (free.w - w) as u64;
+        let leftover_h = (free.h - h) as u64;
+        let short = leftover_w.min(leftover_h);
+        let long = leftover_w.max(leftover_h);
+
+        match self.heuristic {
+            PackHeuristic::BestShortSideFit => (short, long),
+            PackHeuristic::BestLongSideFit => (long, short),
+            PackHeuristic::BestAreaFit => (free.w as u64 * free.h as u64 - w as u64 * h as u64, short),
+            PackHeuristic::BottomLeft => ((free.y + h) as u64, free.x as u64),
+        }
+    }
+
+    /// Replace every free rectangle overlapping used with the up to four maximal rectangles around it
+    fn split_free(&mut self, used: &Rect) {
+        let mut res = Vec::with_capacity(self.free.len() + 4);
+
+        for f in &self.free {
+            if !f.intersects(used) {
+                res.push(*f);
+                continue;
+            }
+
+            if used.x > f.x {
+                res.push(Rect { x: f.x, y: f.y, w: used.x - f.x, h: f.h });
+            }
+            if used.right() < f.right() {
+                res.push(Rect { x: used.right(), y: f.y, w: f.right() - used.right(), h: f.h });
+            }
+            if used.y > f.y {
+                res.push(Rect { x: f.x, y: f.y, w: f.w, h: used.y - f.y });
+            }
+            if used.bottom() < f.bottom() {
+                res.push(Rect { x: f.x, y: used.bottom(), w: f.w, h: f.bottom() - used.bottom() });
+            }
+        }
+
+        self.free = res;
+    }
+
+    /// Remove free rectangles contained in another one
+    fn prune_free(&mut self) {
+        let free = &self.free;
+        let keep : Vec::<bool> = (0..free.len()).map(|i| {
+            !(0..free.len()).any(|j| {
+                // of two equal rectangles keep the first
+                i != j && free[j].contains(&free[i]) && (free[j] != free[i] || j < i)
+            })
+        }).collect();
+
+        self.free = self.free.iter().zip(keep).filter(|(_, k)| *k).map(|(f, _)| *f).collect();
+    }
+}
+
+
+/// Dimensions of the atlas pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasSize {
+    Fixed(u32, u32),
+    /// Smallest size that fits all glyphs on one page
+    Auto,
+}
+
+impl std::str::FromStr for AtlasSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("invalid atlas size '{}', expected <size>, <width>x<height> or auto", s);
+
+        if s == "auto" {
+            return Ok(AtlasSize::Auto);
+        }
+
+        let (w, h) = s.split_once('x').unwrap_or((s, s));
+        let w : u32 = w.parse().map_err(|_| err())?;
+        let h : u32 = h.parse().map_err(|_| err())?;
+        if w == 0 || h == 0 {
+            return Err(err());
+        }
+
+        Ok(AtlasSize::Fixed(w, h))
+    }
+}
+
+
+/// Pack the rectangles onto as many w * h pages as needed, in the sort order. A rectangle goes on the first page with room for it.
+/// Returns the page and placement for each of the sizes, or Err with the index of a rectangle larger than a page
+pub fn pack_pages(sizes: &[(u32, u32)], w: u32, h: u32, heuristic: PackHeuristic, sort: SortOrder) -> Result<Vec::<(usize, Rect)>, usize> {
+
+    let mut packers : Vec::<MaxRectsPacker> = vec![];
+    let mut placements = vec![(0, Rect { x: 0, y: 0, w: 0, h: 0 }); sizes.len()];
+
+    for i in sort.order(sizes) {
+        let (rw, rh) = sizes[i];
+
+        // earlier pages get filled up with the smaller rectangles
+        let placement = packers.iter_mut().enumerate()
+            .find_map(|(page, packer)| packer.insert(rw, rh).map(|rect| (page, rect)));
+
+        placements[i] = match placement {
+            Some(p) => p,
+            None => {
+                let mut packer = MaxRectsPacker::new(w, h, heuristic);
+                let rect = packer.insert(rw, rh).ok_or(i)?;
+                packers.push(packer);
+                (packers.len() - 1, rect)
+            }
+        };
+    }
+
+    Ok(placements)
+}
+
+
+/// Smallest width and height, at most max, where fits returns true. Power of two sizes, or any size when power_of_two is false.
+/// Sizes with less area than min_area are not tried. None when not even max * max fits
+pub fn auto_size<F: Fn(u32, u32) -> bool>(fits: F, min_area: u64, max: u32, power_of_two: bool) -> Option<(u32, u32)> {
+
+    if power_of_two {
+        let pots : Vec::<u32> = (0..32).map(|i| 1 << i).take_while(|&s| s <= max).collect();
+
+        // smallest area first, the squarer one of equal areas, width larger than height when not square
+        let mut candidates : Vec::<(u32, u32)> = pots.iter()
+            .flat_map(|&w| pots.iter().map(move |&h| (w, h)))
+            .filter(|&(w, h)| h <= w && w as u64 * h as u64 >= min_area)
+            .collect();
+        candidates.sort_by_key(|&(w, h)| (w as u64 * h as u64, w - h));
+
+        return candidates.into_iter().find(|&(w, h)| fits(w, h));
+    }
+
+    if !fits(max, max) {
+        return None;
+    }
+
+    // binary search assumes a larger size never packs worse, true for all practical purposes.
+    // First the smallest square, then shrink the height
+    let smallest = |lo: u32, hi: u32, f: &dyn Fn(u32) -> bool| {
+        let (mut lo, mut hi) = (lo.min(hi), hi);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if f(mid) { hi = mid; } else { lo = mid + 1; }
+        }
+        hi
+    };
+
+    let min_side = ((min_area as f64).sqrt().ceil() as u32).max(1);
+    let side = smallest(min_side, max, &|s| fits(s, s));
+
+    let min_h = (min_area.div_ceil(side as u64) as u32).max(1);
+    let h = smallest(min_h, side, &|h| fits(side, h));
+
+    Some((side, h))
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const HEURISTICS: [PackHeuristic; 4] = [
+        PackHeuristic::BestShortSideFit,
+        PackHeuristic::BestLongSideFit,
+        PackHeuristic::BestAreaFit,
+        PackHeuristic::BottomLeft
+    ];
+
+    #[test]
+    fn exact_fit() {
+        for heuristic in HEURISTICS {
+            let mut packer = MaxRectsPacker::new(4, 4, heuristic);
+            for _ in 0..4 {
+                assert!(packer.insert(2, 2).is_some(), "{:?}", heuristic);
+            }
+            assert_eq!(None, packer.insert(1, 1));
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        let mut packer = MaxRectsPacker::new(16, 16, PackHeuristic::BestShortSideFit);
+        assert_eq!(None, packer.insert(17, 1));
+        assert_eq!(Some(Rect { x: 0, y: 0, w: 16, h: 16 }), packer.insert(16, 16));
+    }
+
+    #[test]
+    fn no_overlaps() {
+        // simple lcg so the test is deterministic without extra dependencies
+        let mut seed = 12345u32;
+        let mut rand = |max: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            1 + (seed >> 16) % max
+        };
+        let sizes : Vec::<(u32, u32)> = (0..200).map(|_| (rand(20), rand(30))).collect();
+
+        for heuristic in HEURISTICS {
+            let mut packer = MaxRectsPacker::new(256, 256, heuristic);
+            let mut placed : Vec::<Rect> = vec![];
+
+            for i in SortOrder::Height.order(&sizes) {
+                let rect = packer.insert(sizes[i].0, sizes[i].1).unwrap();
+                assert!(rect.right() <= 256 && rect.bottom() <= 256);
+                assert!(placed.iter().all(|p| !p.intersects(&rect)), "{:?} {:?}", heuristic, rect);
+                placed.push(rect);
+            }
+        }
+    }
+
+    #[test]
+    fn pages_overflow() {
+        let sizes = [(8, 8); 5];
+        let placements = pack_pages(&sizes, 16, 16, PackHeuristic::BestShortSideFit, SortOrder::Height).unwrap();
+
+        let pages : Vec::<usize> = placements.iter().map(|(page, _)| *page).collect();
+        assert_eq!(vec![0, 0, 0, 0, 1], pages);
+        assert_eq!(Err(1), pack_pages(&[(8, 8), (17, 1)], 16, 16, PackHeuristic::BestShortSideFit, SortOrder::Height));
+    }
+
+    #[test]
+    fn auto_size_smallest() {
+        // fits when the area is large enough and both sides at least 10
+        let fits = |w: u32, h: u32| w >= 10 && h >= 10 && w * h >= 300;
+
+        assert_eq!(Some((32, 16)), auto_size(fits, 300, 1024, true));
+        assert_eq!(Some((18, 17)), auto_size(fits, 300, 1024, false));
+        assert_eq!(None, auto_size(fits, 300, 16, true));
+        assert_eq!(None, auto_size(fits, 300, 16, false));
+    }
+
+    #[test]
+    fn parse_atlas_size() {
+        assert_eq!(Ok(AtlasSize::Fixed(512, 512)), "512".parse());
+        assert_eq!(Ok(AtlasSize::Fixed(1024, 256)), "1024x256".parse());
+        assert_eq!(Ok(AtlasSize::Auto), "auto".parse());
+        assert!("0".parse::<AtlasSize>().is_err());
+        assert!("12x".parse::<AtlasSize>().is_err());
+    }
+
+    #[test]
+    fn sort_is_stable() {
+        let sizes = [(1, 2), (3, 4), (4, 3), (2, 2), (4, 3)];
+        assert_eq!(vec![1, 2, 4, 3, 0], SortOrder::Height.order(&sizes));
+        assert_eq!(vec![1, 2, 4, 3, 0], SortOrder::Area.order(&sizes));
+    }
+}
@@ -0,0 +1,672 @@
+//! Signed distance fields from glyph bitmaps using an exact euclidean distance transform
+//! Felzenszwalb & Huttenlocher, "Distance Transforms of Sampled Functions"
+//! https://cs.brown.edu/people/pfelzens/papers/dt-final.pdf
+
+const INF: f64 = 1e20;
+
+
+/// Compute the signed distance field of a bitmap, with padding added on each side.
+/// A pixel is inside when the bitmap value is > 0.
+/// Returns (buf_w + 2 * padding) * (buf_h + 2 * padding) values in range 0.0..1.0
+/// where 0.5 is the border, inside > 0.5 and distances of spread or more are clamped to 0.0 and 1.0
+pub fn distance_field(buffer: &[u8], buf_w: u32, buf_h: u32, padding: u32, spread: u32) -> Vec<f64> {
+
+    let w = (buf_w + padding * 2) as usize;
+    let h = (buf_h + padding * 2) as usize;
+
+    let mut inside = vec![false; w * h];
+    for y in 0..buf_h {
+        for x in 0..buf_w {
+            inside[(y + padding) as usize * w + (x + padding) as usize] = buffer[(y * buf_w + x) as usize] > 0;
+        }
+    }
+
+    // squared distance to closest outside pixel, 0 for outside pixels
+    let mut to_outside : Vec::<f64> = inside.iter().map(|&i| if i { INF } else { 0.0 }).collect();
+    // squared distance to closest inside pixel, 0 for inside pixels
+    let mut to_inside : Vec::<f64> = inside.iter().map(|&i| if i { 0.0 } else { INF }).collect();
+
+    edt_2d(&mut to_outside, w, h);
+    edt_2d(&mut to_inside, w, h);
+
+    inside.iter().enumerate().map(|(i, &is_inside)| {
+        // outside become negative inside positive
+        let dist = if is_inside {
+            f64::sqrt(to_outside[i])
+        } else {
+            - f64::sqrt(to_inside[i])
+        };
+
+        encode(dist, spread)
+    }).collect()
+}
+
+
+/// Compute the signed distance field of an anti aliased bitmap, with padding added on each side.
+/// Uses the coverage of the edge pixels to estimate where inside the pixel the edge is, instead of
+/// snapping the edge to whole pixels.
+/// Same output layout and encoding as distance_field
+/// Gustavson & Strand, "Anti-aliased Euclidean distance transform"
+pub fn distance_field_aa(buffer: &[u8], buf_w: u32, buf_h: u32, padding: u32, spread: u32) -> Vec<f64> {
+
+    let w = (buf_w + padding * 2) as usize;
+    let h = (buf_h + padding * 2) as usize;
+
+    // coverage in 0.0..1.0
+    let mut img = vec![0.0; w * h];
+    for y in 0..buf_h {
+        for x in 0..buf_w {
+            img[(y + padding) as usize * w + (x + padding) as usize] = buffer[(y * buf_w + x) as usize] as f64 / 255.0;
+        }
+    }
+
+    let (gx, gy) = gradient(&img, w, h);
+
+    // distance from outside pixels to the shape
+    let to_shape = AaEdt::new(&img, &gx, &gy, w, h).run();
+
+    // distance from inside pixels to the background, by running on the inverted image
+    // the gradient only matter up to sign, so it can be reused
+    let inverted : Vec::<f64> = img.iter().map(|a| 1.0 - a).collect();
+    let to_background = AaEdt::new(&inverted, &gx, &gy, w, h).run();
+
+    to_shape.iter().zip(to_background.iter()).map(|(&out_d, &in_d)| {
+        // both are <= 0 on the side where they are not meaningful
+        let dist = in_d.max(0.0) - out_d.max(0.0);
+        encode(dist, spread)
+    }).collect()
+}
+
+
+/// Map a signed distance in pixels to 0.0..1.0, distances of spread or more are clamped
+pub fn encode(dist: f64, spread: u32) -> f64 {
+    let spread = spread as f64;
+    let scaled = dist.max(-spread).min(spread) / spread;
+
+    // map from [-1.0..1.0] to [-0.5..0.5] and + 0.5 to be in [0.0..1.0]
+    scaled / 2.0 + 0.5
+}
+
+
+/// In place squared euclidean distance transform of a w * h grid.
+/// Cells with value 0 are the targets, all others should be INF
+fn edt_2d(grid: &mut [f64], w: usize, h: usize) {
+
+    let n = usize::max(w, h);
+    let mut f = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    let mut v = vec![0; n];
+    let mut z = vec![0.0; n + 1];
+
+    // columns
+    for x in 0..w {
+        for y in 0..h {
+            f[y] = grid[y * w + x];
+        }
+        edt_1d(&f[..h], &mut d[..h], &mut v, &mut z);
+        for y in 0..h {
+            grid[y * w + x] = d[y];
+        }
+    }
+
+    // rows
+    for y in 0..h {
+        f[..w].copy_from_slice(&grid[y * w..(y + 1) * w]);
+        edt_1d(&f[..w], &mut d[..w], &mut v, &mut z);
+        grid[y * w..(y + 1) * w].copy_from_slice(&d[..w]);
+    }
+}
+
+
+/// 1d squared distance transform, lower envelope of the parabolas rooted at each sample of f.
+/// v and z are scratch buffers of at least f.len() and f.len() + 1
+fn edt_1d(f: &[f64], d: &mut [f64], v: &mut [usize], z: &mut [f64]) {
+
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+
+    // index of rightmost parabola in lower envelope
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = f64::NEG_INFINITY;
+    z[1] = f64::INFINITY;
+
+    // intersection of the parabolas rooted at q and p
+    let intersect = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2.0 * q as f64 - 2.0 * p as f64)
+    };
+
+    for q in 1..n {
+        let mut s = intersect(q, v[k]);
+
+        // z[0] is -inf so this stops at k == 0
+        while s <= z[k] {
+            k -= 1;
+            s = intersect(q, v[k]);
+        }
+
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = f64::INFINITY;
+    }
+
+    k = 0;
+    for (q, dq) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f64 {
+            k += 1;
+        }
+        let diff = q as f64 - v[k] as f64;
+        *dq = diff * diff + f[v[k]];
+    }
+}
+
+
+/// Average each factor * factor block of a w * h grid. w and h have to be multiples of factor
+pub fn downsample(values: &[f64], w: usize, h: usize, factor: usize) -> Vec::<f64> {
+    if factor == 1 {
+        return values.to_vec();
+    }
+
+    let out_w = w / factor;
+    let out_h = h / factor;
+    let mut res = vec![0.0; out_w * out_h];
+
+    for y in 0..h {
+        for x in 0..w {
+            res[(y / factor) * out_w + x / factor] += values[y * w + x];
+        }
+    }
+
+    let block = (factor * factor) as f64;
+    res.iter_mut().for_each(|v| *v /= block);
+    res
+}
+
+// Distance used for pixels where the closest edge has not been found yet
+const FAR: f64 = 1e6;
+
+
+/// Normalized gradient of the coverage, only computed for edge pixels not on the image border.
+/// Other pixels get a zero gradient
+fn gradient(img: &[f64], w: usize, h: usize) -> (Vec::<f64>, Vec::<f64>) {
+
+    let mut gx = vec![0.0; w * h];
+    let mut gy = vec![0.0; w * h];
+
+    for y in 1..h.saturating_sub(1) {
+        for x in 1..w.saturating_sub(1) {
+            let k = y * w + x;
+            if img[k] <= 0.0 || img[k] >= 1.0 {
+                continue;
+            }
+
+            let dx = - img[k - w - 1] - std::f64::consts::SQRT_2 * img[k - 1] - img[k + w - 1]
+                + img[k - w + 1] + std::f64::consts::SQRT_2 * img[k + 1] + img[k + w + 1];
+            let dy = - img[k - w - 1] - std::f64::consts::SQRT_2 * img[k - w] - img[k - w + 1]
+                + img[k + w - 1] + std::f64::consts::SQRT_2 * img[k + w] + img[k + w + 1];
+
+            let len = f64::sqrt(dx * dx + dy * dy);
+            if len > 0.0 {
+                gx[k] = dx / len;
+                gy[k] = dy / len;
+            }
+        }
+    }
+
+    (gx, gy)
+}
+
+
+/// Distance from the center of a pixel with coverage a to the edge going through it,
+/// assuming the edge is a straight line with normal (gx, gy). Negative when the center is inside.
+fn edge_df(gx: f64, gy: f64, a: f64) -> f64 {
+
+    if gx == 0.0 || gy == 0.0 {
+        // axis aligned edge, or unknown direction
+        return 0.5 - a;
+    }
+
+    let len = f64::sqrt(gx * gx + gy * gy);
+    let mut gx = (gx / len).abs();
+    let mut gy = (gy / len).abs();
+    if gx < gy {
+        std::mem::swap(&mut gx, &mut gy);
+    }
+
+    // coverage where the edge passes through a corner of the pixel
+    let a1 = 0.5 * gy / gx;
+
+    if a < a1 {
+        0.5 * (gx + gy) - f64::sqrt(2.0 * gx * gy * a)
+    } else if a < 1.0 - a1 {
+        (0.5 - a) * gx
+    } else {
+        -0.5 * (gx + gy) + f64::sqrt(2.0 * gx * gy * (1.0 - a))
+    }
+}
+
+
+/// Vector propagation distance transform, every pixel keep the offset to the closest pixel with
+/// coverage > 0 and the distance is offset length plus the sub pixel distance to the edge in that pixel
+struct AaEdt<'a> {
+    img: &'a [f64],
+    gx: &'a [f64],
+    gy: &'a [f64],
+    w: usize,
+    h: usize,
+    // offset from pixel to closest edge pixel, so closest is (x - dist_x, y - dist_y)
+    dist_x: Vec::<i32>,
+    dist_y: Vec::<i32>,
+    dist: Vec::<f64>,
+}
+
+impl<'a> AaEdt<'a> {
+
+    fn new(img: &'a [f64], gx: &'a [f64], gy: &'a [f64], w: usize, h: usize) -> Self {
+        let dist = img.iter().enumerate().map(|(i, &a)| {
+            if a <= 0.0 {
+                FAR
+            } else if a < 1.0 {
+                edge_df(gx[i], gy[i], a)
+            } else {
+                0.0
+            }
+        }).collect();
+
+        AaEdt {
+            img, gx, gy, w, h,
+            dist_x: vec![0; w * h],
+            dist_y: vec![0; w * h],
+            dist
+        }
+    }
+
+    /// Sweep forward and backward until no distances change, returns the distances
+    fn run(mut self) -> Vec::<f64> {
+
+        let (w, h) = (self.w, self.h);
+
+        loop {
+            let mut changed = false;
+
+            // top to bottom
+            for y in 0..h {
+                for x in 0..w {
+                    for (ox, oy) in [(0, -1), (-1, -1), (1, -1), (-1, 0)] {
+                        changed |= self.relax(x, y, ox, oy);
+                    }
+                }
+                for x in (0..w).rev() {
+                    changed |= self.relax(x, y, 1, 0);
+                }
+            }
+
+            // bottom to top
+            for y in (0..h).rev() {
+                for x in (0..w).rev() {
+                    for (ox, oy) in [(1, 0), (0, 1), (-1, 1), (1, 1)] {
+                        changed |= self.relax(x, y, ox, oy);
+                    }
+                }
+                for x in 0..w {
+                    changed |= self.relax(x, y, -1, 0);
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        self.dist
+    }
+
+    /// Try the closest edge pixel of the neighbour at (x + ox, y + oy) as closest for (x, y)
+    fn relax(&mut self, x: usize, y: usize, ox: i32, oy: i32) -> bool {
+        const EPSILON: f64 = 1e-3;
+
+        let c = y * self.w + x;
+        if self.dist[c] <= 0.0 {
+            return false;
+        }
+
+        let nx = x as i32 + ox;
+        let ny = y as i32 + oy;
+        if nx < 0 || ny < 0 || nx >= self.w as i32 || ny >= self.h as i32 {
+            return false;
+        }
+
+        let n = ny as usize * self.w + nx as usize;
+        let closest = (ny - self.dist_y[n]) as usize * self.w + (nx - self.dist_x[n]) as usize;
+
+        let new_dx = self.dist_x[n] - ox;
+        let new_dy = self.dist_y[n] - oy;
+        let new_dist = self.dist_aa(closest, new_dx, new_dy);
+
+        if new_dist < self.dist[c] - EPSILON {
+            self.dist_x[c] = new_dx;
+            self.dist_y[c] = new_dy;
+            self.dist[c] = new_dist;
+            return true;
+        }
+        false
+    }
+
+    /// Distance to the edge in pixel closest, from a pixel offset (dx, dy) from it
+    fn dist_aa(&self, closest: usize, dx: i32, dy: i32) -> f64 {
+        let a = self.img[closest].clamp(0.0, 1.0);
+        if a == 0.0 {
+            return FAR;
+        }
+
+        let di = f64::sqrt((dx * dx + dy * dy) as f64);
+        let df = if di == 0.0 {
+            edge_df(self.gx[closest], self.gy[closest], a)
+        } else {
+            // use the direction to the pixel as the edge normal
+            edge_df(dx as f64, dy as f64, a)
+        };
+
+        di + df
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    // Brute force distance search the distance field used to be generated with.
+    // Kept as the reference the transform is tested against.
+    // find min dist to a border pixel
+    // return in range 0.0..1.0 where 0.5 border
+    fn px_value(buffer: &[u8], buf_w: i32, buf_h: i32, padding: i32, sdf_x: i32, sdf_y: i32, spread: i32) -> f64 {
+
+        let outside = sdf_x < padding || sdf_x >= buf_w + padding || sdf_y < padding || sdf_y >= buf_h + padding;
+
+        // set state (inside or out)
+        let mut state = 0;
+        let state_idx = ((sdf_y - padding ) * buf_w + sdf_x - padding) as usize;
+        if !outside && buffer[state_idx] > 0 {
+            state = 1;
+        }
+
+        let x_start = sdf_x - spread;
+        let x_end = sdf_x + spread;
+
+        let y_start = sdf_y - spread;
+        let y_end = sdf_y + spread;
+
+        let mut min_dist_squared = spread * spread;
+
+        let max_dist = f64::sqrt(min_dist_squared as f64);
+        // iterate only over pixels in buffer, since all the others outside will be further away
+
+        // x and y is index into the final sdf image
+        for y in y_start..y_end {
+            for x in x_start..x_end {
+
+                // map to x and y in buffer. and if outside skip
+                let buf_x = x - padding;
+                let buf_y = y - padding;
+
+                if buf_x < 0 || buf_x >= buf_w || buf_y < 0 || buf_y >= buf_h {
+                    continue;
+                }
+
+                let buffer_v = buffer[(buf_y * buf_w + buf_x) as usize].min(1);
+
+                if buffer_v != state {
+                    let x_diff = sdf_x - x;
+                    let y_diff = sdf_y - y;
+
+                    min_dist_squared = i32::min(min_dist_squared, x_diff * x_diff + y_diff * y_diff);
+                }
+            }
+        }
+
+        let min_dist = f64::sqrt(min_dist_squared as f64);
+
+        assert!(min_dist <= max_dist);
+
+        // outside become negative inside positive
+        let mut mul = 1.0;
+        if state == 0 {
+            mul = -1.0
+        }
+
+        let scaled = (min_dist / max_dist) * mul;
+
+        // map from [-1.0..1.0] to [-0.5..0.5] and + 0.5 to be in [0.0..1.0]
+        let res = scaled / 2.0 + 0.5;
+        assert!((state == 1 && res > 0.5) || (state == 0 && res <= 0.5));
+        res
+    }
+
+
+    fn assert_same_as_px_value(buffer: &[u8], w: u32, h: u32, padding: u32, spread: u32) {
+        let field = distance_field(buffer, w, h, padding, spread);
+
+        let sdf_w = w + padding * 2;
+        let sdf_h = h + padding * 2;
+        assert_eq!((sdf_w * sdf_h) as usize, field.len());
+
+        for y in 0..sdf_h {
+            for x in 0..sdf_w {
+                let expected = px_value(buffer, w as i32, h as i32, padding as i32, x as i32, y as i32, spread as i32);
+                let actual = field[(y * sdf_w + x) as usize];
+                assert!((expected - actual).abs() < 1e-9, "({}, {}) expected {} got {}", x, y, expected, actual);
+            }
+        }
+    }
+
+
+    #[test]
+    fn test_px_value() {
+
+        let mut buffer = [1; 8*8];
+
+        for v in buffer.iter_mut().take(16) {
+            *v = 0;
+        }
+
+        let padding = 4;
+        let spread = 8;
+
+        let x = 4;
+        let y = 4;
+        let v = px_value(&buffer, 8, 8, padding, padding + x, padding + y, spread);
+
+        // closest outside pixel is 3 rows up, so 3/8 of the way from the border to max inside
+        assert_eq!(0.5 + 3.0 / 16.0, v);
+    }
+
+
+    #[test]
+    fn edt_1d_single_target() {
+        let f = [INF, INF, 0.0, INF, INF, INF];
+        let mut d = [0.0; 6];
+        let mut v = [0; 6];
+        let mut z = [0.0; 7];
+        edt_1d(&f, &mut d, &mut v, &mut z);
+
+        assert_eq!([4.0, 1.0, 0.0, 1.0, 4.0, 9.0], d);
+    }
+
+
+    // px_value only looks inside the bitmap, so shapes in the tests keep a 1 pixel empty border
+    // to not depend on that
+
+    #[test]
+    fn same_as_px_value_circle() {
+        let (w, h) = (17, 15);
+        let mut buffer = vec![0; (w * h) as usize];
+        for y in 0..h {
+            for x in 0..w {
+                let dx = x as f64 - 8.0;
+                let dy = y as f64 - 7.0;
+                if dx * dx + dy * dy < 36.0 {
+                    buffer[(y * w + x) as usize] = 200;
+                }
+            }
+        }
+
+        assert_same_as_px_value(&buffer, w, h, 3, 4);
+        assert_same_as_px_value(&buffer, w, h, 0, 10);
+    }
+
+
+    #[test]
+    fn same_as_px_value_random() {
+        // simple lcg, so the test is deterministic
+        let mut seed : u32 = 12345;
+        let mut next = || {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) & 0xff
+        };
+
+        for (w, h) in [(5, 5), (12, 7), (9, 20)] {
+            let mut buffer = vec![0; (w * h) as usize];
+            for y in 1..h - 1 {
+                for x in 1..w - 1 {
+                    if next() > 150 {
+                        buffer[(y * w + x) as usize] = next() as u8 | 1;
+                    }
+                }
+            }
+
+            assert_same_as_px_value(&buffer, w, h, 2, 3);
+            assert_same_as_px_value(&buffer, w, h, 4, 6);
+        }
+    }
+
+
+    #[test]
+    fn edge_is_half_and_spread_is_clamped() {
+        assert_eq!(0.5, encode(0.0, 4));
+        assert_eq!(1.0, encode(4.0, 4));
+        assert_eq!(0.0, encode(-4.0, 4));
+        assert_eq!(1.0, encode(100.0, 4));
+        assert_eq!(0.0, encode(-100.0, 4));
+
+        // left 10 columns inside
+        let (w, h) = (30, 3);
+        let buffer : Vec::<u8> = (0..w * h).map(|i| if i % w < 10 { 255 } else { 0 }).collect();
+
+        let spread = 4;
+        let field = distance_field(&buffer, w, h, 0, spread);
+        let row = &field[w as usize..2 * w as usize];
+
+        // the pixels on each side of the edge are one pixel from the other side, the same step from 0.5
+        assert_eq!(0.5 + 1.0 / 8.0, row[9]);
+        assert_eq!(0.5 - 1.0 / 8.0, row[10]);
+        for (x, &actual) in row.iter().enumerate() {
+            let expected = px_value(&buffer, w as i32, h as i32, 0, x as i32, 1, spread as i32);
+            assert!((expected - actual).abs() < 1e-9, "x={} expected {} got {}", x, expected, actual);
+        }
+
+        // distances of spread or more are clamped
+        assert!(row[..=6].iter().all(|&v| v == 1.0));
+        assert!(row[13..].iter().all(|&v| v == 0.0));
+    }
+
+
+    #[test]
+    fn empty_bitmap() {
+        let field = distance_field(&[], 0, 0, 2, 4);
+        assert_eq!(16, field.len());
+        assert!(field.iter().all(|&v| v == 0.0));
+
+        let field = distance_field_aa(&[], 0, 0, 2, 4);
+        assert_eq!(16, field.len());
+        assert!(field.iter().all(|&v| v == 0.0));
+    }
+
+
+    #[test]
+    fn downsample_averages_blocks() {
+        let values = [
+            0.0, 1.0, 2.0, 2.0,
+            1.0, 0.0, 2.0, 2.0,
+        ];
+        assert_eq!(vec![0.5, 2.0], downsample(&values, 4, 2, 2));
+        assert_eq!(values.to_vec(), downsample(&values, 4, 2, 1));
+    }
+
+
+    // signed distance in pixels from the encoded value
+    fn decode(v: f64, spread: u32) -> f64 {
+        (v - 0.5) * 2.0 * spread as f64
+    }
+
+
+    #[test]
+    fn aa_binary_edge_is_on_pixel_border() {
+        // left 4 columns inside
+        let (w, h) = (10, 6);
+        let buffer : Vec::<u8> = (0..w * h).map(|i| if i % w < 4 { 255 } else { 0 }).collect();
+
+        let spread = 8;
+        let field = distance_field_aa(&buffer, w, h, 0, spread);
+
+        for x in 0..w {
+            // edge is at x = 4.0, pixel centers at x + 0.5
+            let expected = 4.0 - (x as f64 + 0.5);
+            let actual = decode(field[(2 * w + x) as usize], spread);
+            assert!((expected - actual).abs() < 1e-6, "x={} expected {} got {}", x, expected, actual);
+        }
+    }
+
+
+    #[test]
+    fn aa_uses_coverage() {
+        // left 4 columns inside, column 4 is a quarter covered, so the edge is at x = 4.25
+        let (w, h) = (10, 6);
+        let buffer : Vec::<u8> = (0..w * h).map(|i| match i % w {
+            0..=3 => 255,
+            4 => 64,
+            _ => 0
+        }).collect();
+
+        let spread = 8;
+        let field = distance_field_aa(&buffer, w, h, 0, spread);
+
+        for x in 0..w {
+            let expected = 4.0 + 64.0 / 255.0 - (x as f64 + 0.5);
+            let actual = decode(field[(3 * w + x) as usize], spread);
+            assert!((expected - actual).abs() < 1e-6, "x={} expected {} got {}", x, expected, actual);
+        }
+    }
+
+
+    #[test]
+    fn aa_diagonal_edge() {
+        // half plane x + y < 8, with exact coverage for the pixels on the edge
+        let (w, h) = (12, 12);
+        let mut buffer = vec![0; (w * h) as usize];
+        for y in 0..h {
+            for x in 0..w {
+                buffer[(y * w + x) as usize] = match x + y {
+                    s if s < 7 => 255,
+                    7 => 128,
+                    _ => 0
+                };
+            }
+        }
+
+        let spread = 6;
+        let field = distance_field_aa(&buffer, w, h, 1, spread);
+        let sdf_w = w + 2;
+
+        // check along the anti diagonal through the middle, away from the image borders
+        for (x, y) in [(3, 3), (4, 3), (4, 4), (5, 4), (5, 5), (6, 6)] {
+            let expected = (8.0 - (x as f64 + 0.5) - (y as f64 + 0.5)) / std::f64::consts::SQRT_2;
+            let actual = decode(field[((y + 1) * sdf_w + x + 1) as usize], spread);
+            assert!((expected - actual).abs() < 0.1, "({}, {}) expected {} got {}", x, y, expected, actual);
+        }
+    }
+}
@@ -0,0 +1,618 @@
+//! Glyph outlines as contours of line and bezier segments, with exact distances to them.
+//! Used to generate distance fields directly from the vector outline instead of a rendered bitmap.
+
+use freetype::outline::{Outline, Curve};
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub fn new(x: f64, y: f64) -> Self {
+        Vec2 { x, y }
+    }
+
+    pub fn dot(self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn cross(self, other: Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Vec2 {
+        let len = self.length();
+        if len == 0.0 {
+            return Vec2::new(0.0, 0.0);
+        }
+        self * (1.0 / len)
+    }
+}
+
+
+/// Distance to an edge, with the dot product between the edge direction and the direction to the point
+/// when the closest point is an end point. Used to pick the right edge when two edges are equally close to a corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignedDistance {
+    pub distance: f64,
+    pub dot: f64,
+}
+
+impl SignedDistance {
+    pub const INFINITE: SignedDistance = SignedDistance { distance: f64::NEG_INFINITY, dot: 1.0 };
+
+    pub fn closer_than(&self, other: &SignedDistance) -> bool {
+        let (a, b) = (self.distance.abs(), other.distance.abs());
+        a < b || (a == b && self.dot < other.dot)
+    }
+}
+
+impl std::ops::Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, o: Vec2) -> Vec2 {
+        Vec2::new(self.x + o.x, self.y + o.y)
+    }
+}
+
+impl std::ops::Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, o: Vec2) -> Vec2 {
+        Vec2::new(self.x - o.x, self.y - o.y)
+    }
+}
+
+impl std::ops::Mul<f64> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, s: f64) -> Vec2 {
+        Vec2::new(self.x * s, self.y * s)
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Line(Vec2, Vec2),
+    Quad(Vec2, Vec2, Vec2),
+    Cubic(Vec2, Vec2, Vec2, Vec2),
+}
+
+impl Segment {
+
+    pub fn point(&self, t: f64) -> Vec2 {
+        match *self {
+            Segment::Line(p0, p1) => p0 + (p1 - p0) * t,
+            Segment::Quad(p0, p1, p2) => {
+                let s = 1.0 - t;
+                p0 * (s * s) + p1 * (2.0 * s * t) + p2 * (t * t)
+            },
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let s = 1.0 - t;
+                p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+            }
+        }
+    }
+
+    /// First derivative
+    pub fn direction(&self, t: f64) -> Vec2 {
+        match *self {
+            Segment::Line(p0, p1) => p1 - p0,
+            Segment::Quad(p0, p1, p2) => {
+                (p1 - p0) * (2.0 * (1.0 - t)) + (p2 - p1) * (2.0 * t)
+            },
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let s = 1.0 - t;
+                (p1 - p0) * (3.0 * s * s) + (p2 - p1) * (6.0 * s * t) + (p3 - p2) * (3.0 * t * t)
+            }
+        }
+    }
+
+    /// Second derivative
+    fn direction_change(&self, t: f64) -> Vec2 {
+        match *self {
+            Segment::Line(_, _) => Vec2::new(0.0, 0.0),
+            Segment::Quad(p0, p1, p2) => (p2 - p1 * 2.0 + p0) * 2.0,
+            Segment::Cubic(p0, p1, p2, p3) => {
+                (p2 - p1 * 2.0 + p0) * (6.0 * (1.0 - t)) + (p3 - p2 * 2.0 + p1) * (6.0 * t)
+            }
+        }
+    }
+
+    pub fn end(&self) -> Vec2 {
+        self.point(1.0)
+    }
+
+    /// Direction of the segment at t. Falls back to the direction between the end points when the
+    /// derivative is zero, which happens when a control point is on top of an end point
+    pub fn tangent(&self, t: f64) -> Vec2 {
+        let d = self.direction(t);
+        if d.x == 0.0 && d.y == 0.0 {
+            return self.end() - self.point(0.0);
+        }
+        d
+    }
+
+    /// Split into the parts before and after t
+    pub fn split(&self, t: f64) -> (Segment, Segment) {
+        let lerp = |a: Vec2, b: Vec2| a + (b - a) * t;
+        match *self {
+            Segment::Line(p0, p1) => {
+                let m = lerp(p0, p1);
+                (Segment::Line(p0, m), Segment::Line(m, p1))
+            },
+            Segment::Quad(p0, p1, p2) => {
+                let a = lerp(p0, p1);
+                let b = lerp(p1, p2);
+                let m = lerp(a, b);
+                (Segment::Quad(p0, a, m), Segment::Quad(m, b, p2))
+            },
+            Segment::Cubic(p0, p1, p2, p3) => {
+                let a = lerp(p0, p1);
+                let b = lerp(p1, p2);
+                let c = lerp(p2, p3);
+                let ab = lerp(a, b);
+                let bc = lerp(b, c);
+                let m = lerp(ab, bc);
+                (Segment::Cubic(p0, a, ab, m), Segment::Cubic(m, bc, c, p3))
+            }
+        }
+    }
+
+    pub fn split_in_thirds(&self) -> [Segment; 3] {
+        let (first, rest) = self.split(1.0 / 3.0);
+        let (second, third) = rest.split(0.5);
+        [first, second, third]
+    }
+
+    /// Distance to p, positive when p is left of the segment direction, and the closest t
+    pub fn signed_distance(&self, p: Vec2) -> (SignedDistance, f64) {
+        let t = self.closest_t(p);
+        let qp = p - self.point(t);
+        let dir = self.tangent(t);
+
+        let sign = if dir.cross(qp) >= 0.0 { 1.0 } else { -1.0 };
+        let dot = if t == 0.0 || t == 1.0 {
+            dir.normalize().dot(qp.normalize()).abs()
+        } else {
+            0.0
+        };
+
+        (SignedDistance { distance: sign * qp.length(), dot }, t)
+    }
+
+    /// When the closest point is an end point, and p is beyond it, use the distance to the tangent line
+    /// through the end point instead if it is closer. Makes the distance continuous across corners
+    pub fn pseudo_distance(&self, p: Vec2, distance: SignedDistance, t: f64) -> f64 {
+        let (end, dir, beyond) = if t == 0.0 {
+            let dir = self.tangent(0.0).normalize();
+            (self.point(0.0), dir, (p - self.point(0.0)).dot(dir) < 0.0)
+        } else if t == 1.0 {
+            let dir = self.tangent(1.0).normalize();
+            (self.end(), dir, (p - self.end()).dot(dir) > 0.0)
+        } else {
+            return distance.distance;
+        };
+
+        if beyond {
+            let pseudo = dir.cross(p - end);
+            if pseudo.abs() <= distance.distance.abs() {
+                return pseudo;
+            }
+        }
+        distance.distance
+    }
+
+    /// Parameter t in 0.0..=1.0 of the point on the segment closest to p
+    pub fn closest_t(&self, p: Vec2) -> f64 {
+
+        // candidates are the end points and where the derivative of the squared distance is 0
+        let mut candidates = vec![0.0, 1.0];
+
+        match *self {
+            Segment::Line(p0, p1) => {
+                let d = p1 - p0;
+                let len2 = d.dot(d);
+                if len2 > 0.0 {
+                    candidates.push((p - p0).dot(d) / len2);
+                }
+            },
+            Segment::Quad(p0, p1, p2) => {
+                // B(t) = p0 + 2t * ab + t^2 * br, solve dot(B(t) - p, B'(t)) = 0
+                let qa = p0 - p;
+                let ab = p1 - p0;
+                let br = p2 - p1 - ab;
+                let a = br.dot(br);
+                let b = 3.0 * ab.dot(br);
+                let c = 2.0 * ab.dot(ab) + qa.dot(br);
+                let d = qa.dot(ab);
+                candidates.extend(solve_cubic(a, b, c, d));
+            },
+            Segment::Cubic(_, _, _, _) => {
+                // no closed form, newton iterations from a few starting points
+                const STARTS: usize = 8;
+                const STEPS: usize = 8;
+                for i in 0..=STARTS {
+                    let mut t = i as f64 / STARTS as f64;
+                    for _ in 0..STEPS {
+                        let qe = self.point(t) - p;
+                        let d1 = self.direction(t);
+                        let d2 = self.direction_change(t);
+                        let denom = d1.dot(d1) + qe.dot(d2);
+                        if denom == 0.0 {
+                            break;
+                        }
+                        t -= qe.dot(d1) / denom;
+                        if !(0.0..=1.0).contains(&t) {
+                            break;
+                        }
+                    }
+                    candidates.push(t);
+                }
+            }
+        }
+
+        let mut best_t = 0.0;
+        let mut best_d = f64::INFINITY;
+        for t in candidates {
+            if !(0.0..=1.0).contains(&t) {
+                continue;
+            }
+            let d = (self.point(t) - p).length();
+            if d < best_d {
+                best_d = d;
+                best_t = t;
+            }
+        }
+        best_t
+    }
+
+    /// Unsigned distance from p to the segment
+    pub fn distance(&self, p: Vec2) -> f64 {
+        (self.point(self.closest_t(p)) - p).length()
+    }
+
+    /// Parameter values where the segment splits into parts that are monotonic in y, including 0 and 1
+    fn y_monotonic_splits(&self) -> Vec::<f64> {
+        let mut splits = vec![0.0];
+
+        let roots = match *self {
+            Segment::Line(_, _) => vec![],
+            Segment::Quad(p0, p1, p2) => {
+                let denom = p0.y - 2.0 * p1.y + p2.y;
+                if denom != 0.0 {
+                    vec![(p0.y - p1.y) / denom]
+                } else {
+                    vec![]
+                }
+            },
+            Segment::Cubic(p0, p1, p2, p3) => {
+                // derivative / 3 = a t^2 + b t + c
+                let a = p3.y - 3.0 * p2.y + 3.0 * p1.y - p0.y;
+                let b = 2.0 * (p2.y - 2.0 * p1.y + p0.y);
+                let c = p1.y - p0.y;
+                let mut roots = solve_quadratic(a, b, c);
+                roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                roots
+            }
+        };
+
+        splits.extend(roots.into_iter().filter(|&t| t > 0.0 && t < 1.0));
+        splits.push(1.0);
+        splits
+    }
+
+    /// Winding contribution of the segment for a ray from p towards +x
+    fn winding(&self, p: Vec2) -> i32 {
+        let mut winding = 0;
+
+        let splits = self.y_monotonic_splits();
+        for w in splits.windows(2) {
+            let (mut ta, mut tb) = (w[0], w[1]);
+            let ya = self.point(ta).y;
+            let yb = self.point(tb).y;
+
+            // same half open rule as for polygons, so shared end points are only counted once
+            if (ya <= p.y) == (yb <= p.y) {
+                continue;
+            }
+
+            // bisect for the crossing, the part is monotonic in y
+            let rising = yb > ya;
+            for _ in 0..60 {
+                let tm = 0.5 * (ta + tb);
+                if (self.point(tm).y <= p.y) == rising {
+                    ta = tm;
+                } else {
+                    tb = tm;
+                }
+            }
+
+            if self.point(0.5 * (ta + tb)).x > p.x {
+                winding += if rising { 1 } else { -1 };
+            }
+        }
+
+        winding
+    }
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Contour {
+    pub segments: Vec::<Segment>,
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Shape {
+    pub contours: Vec::<Contour>,
+}
+
+impl Shape {
+
+    /// Build shape from a freetype outline, coordinates are converted from 26.6 to pixels
+    pub fn from_outline(outline: &Outline) -> Self {
+
+        let to_vec = |v: &freetype::Vector| Vec2::new(v.x as f64 / 64.0, v.y as f64 / 64.0);
+
+        let mut contours = vec![];
+        for curves in outline.contours_iter() {
+            let mut segments = vec![];
+            let mut prev = to_vec(curves.start());
+
+            for curve in curves {
+                let seg = match curve {
+                    Curve::Line(p) => Segment::Line(prev, to_vec(&p)),
+                    Curve::Bezier2(c, p) => Segment::Quad(prev, to_vec(&c), to_vec(&p)),
+                    Curve::Bezier3(c1, c2, p) => Segment::Cubic(prev, to_vec(&c1), to_vec(&c2), to_vec(&p)),
+                };
+
+                prev = seg.end();
+
+                // skip the closing line when the contour already ends on the start point
+                if let Segment::Line(a, b) = seg {
+                    if a == b {
+                        continue;
+                    }
+                }
+                segments.push(seg);
+            }
+
+            if !segments.is_empty() {
+                contours.push(Contour { segments });
+            }
+        }
+
+        Shape { contours }
+    }
+
+    /// Sign of the area enclosed by the control polygons. 1.0 when the outer contours are counter clockwise,
+    /// -1.0 when they are clockwise like in TrueType fonts
+    pub fn orientation(&self) -> f64 {
+        let area : f64 = self.contours.iter()
+            .flat_map(|c| c.segments.iter())
+            .map(|s| {
+                let pts = match *s {
+                    Segment::Line(a, b) => vec![a, b],
+                    Segment::Quad(a, b, c) => vec![a, b, c],
+                    Segment::Cubic(a, b, c, d) => vec![a, b, c, d],
+                };
+                pts.windows(2).map(|w| w[0].cross(w[1])).sum::<f64>()
+            })
+            .sum();
+
+        if area < 0.0 { -1.0 } else { 1.0 }
+    }
+
+    /// Nonzero winding rule
+    pub fn is_inside(&self, p: Vec2) -> bool {
+        let winding : i32 = self.contours.iter()
+            .flat_map(|c| c.segments.iter())
+            .map(|s| s.winding(p))
+            .sum();
+        winding != 0
+    }
+
+    /// Distance to the closest segment, positive inside negative outside
+    pub fn signed_distance(&self, p: Vec2) -> f64 {
+        let dist = self.contours.iter()
+            .flat_map(|c| c.segments.iter())
+            .map(|s| s.distance(p))
+            .fold(f64::INFINITY, f64::min);
+
+        if self.is_inside(p) {
+            dist
+        } else {
+            -dist
+        }
+    }
+}
+
+
+/// Real roots of a t^2 + b t + c
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec::<f64> {
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+
+    let disc = b * b - 4.0 * a * c;
+    if disc > 0.0 {
+        let s = disc.sqrt();
+        vec![(-b + s) / (2.0 * a), (-b - s) / (2.0 * a)]
+    } else if disc == 0.0 {
+        vec![-b / (2.0 * a)]
+    } else {
+        vec![]
+    }
+}
+
+
+/// Real roots of a t^3 + b t^2 + c t + d
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec::<f64> {
+    if a.abs() < 1e-12 {
+        return solve_quadratic(b, c, d);
+    }
+
+    // normalized t^3 + a t^2 + b t + c
+    let (a, b, c) = (b / a, c / a, d / a);
+
+    let a2 = a * a;
+    let q = (a2 - 3.0 * b) / 9.0;
+    let r = (a * (2.0 * a2 - 9.0 * b) + 27.0 * c) / 54.0;
+    let r2 = r * r;
+    let q3 = q * q * q;
+    let a = a / 3.0;
+
+    if r2 < q3 {
+        let t = (r / q3.sqrt()).clamp(-1.0, 1.0).acos();
+        let m = -2.0 * q.sqrt();
+        let tau = 2.0 * std::f64::consts::PI;
+        vec![
+            m * (t / 3.0).cos() - a,
+            m * ((t + tau) / 3.0).cos() - a,
+            m * ((t - tau) / 3.0).cos() - a,
+        ]
+    } else {
+        let mut u = (r.abs() + (r2 - q3).sqrt()).cbrt();
+        if r > 0.0 {
+            u = -u;
+        }
+        let v = if u == 0.0 { 0.0 } else { q / u };
+        let mut roots = vec![(u + v) - a];
+        if (u - v).abs() < 1e-12 * (u + v).abs() {
+            roots.push(-0.5 * (u + v) - a);
+        }
+        roots
+    }
+}
+
+
+/// Point shorthand for the shape tests
+#[cfg(test)]
+pub fn v(x: f64, y: f64) -> Vec2 {
+    Vec2::new(x, y)
+}
+
+
+/// Closed contour of straight lines through the points, for the shape tests
+#[cfg(test)]
+pub fn polygon(points: &[Vec2]) -> Contour {
+    let segments = points.iter().zip(points.iter().cycle().skip(1))
+        .map(|(&a, &b)| Segment::Line(a, b))
+        .collect();
+    Contour { segments }
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn brute_force_distance(seg: &Segment, p: Vec2) -> f64 {
+        (0..=10000).map(|i| (seg.point(i as f64 / 10000.0) - p).length()).fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn square_signed_distance() {
+        let shape = Shape { contours: vec![polygon(&[v(0.0, 0.0), v(4.0, 0.0), v(4.0, 4.0), v(0.0, 4.0)])] };
+
+        assert_eq!(2.0, shape.signed_distance(v(2.0, 2.0)));
+        assert_eq!(1.0, shape.signed_distance(v(1.0, 2.0)));
+        assert_eq!(-1.0, shape.signed_distance(v(5.0, 2.0)));
+        assert!((shape.signed_distance(v(5.0, 5.0)) + 2.0_f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn nonzero_winding() {
+        let outer = polygon(&[v(0.0, 0.0), v(10.0, 0.0), v(10.0, 10.0), v(0.0, 10.0)]);
+        // same direction, overlapping, still inside with nonzero
+        let same = polygon(&[v(2.0, 2.0), v(8.0, 2.0), v(8.0, 8.0), v(2.0, 8.0)]);
+        // reversed, makes a hole
+        let hole = polygon(&[v(2.0, 2.0), v(2.0, 8.0), v(8.0, 8.0), v(8.0, 2.0)]);
+
+        let overlap = Shape { contours: vec![outer.clone(), same] };
+        assert!(overlap.is_inside(v(5.0, 5.0)));
+        assert!(overlap.is_inside(v(1.0, 5.0)));
+
+        let ring = Shape { contours: vec![outer, hole] };
+        assert!(!ring.is_inside(v(5.0, 5.0)));
+        assert!(ring.is_inside(v(1.0, 5.0)));
+        assert!(!ring.is_inside(v(11.0, 5.0)));
+        // ray through a vertex
+        assert!(ring.is_inside(v(1.0, 2.0)));
+    }
+
+    #[test]
+    fn quad_distance() {
+        let seg = Segment::Quad(v(0.0, 0.0), v(5.0, 10.0), v(10.0, 0.0));
+        for p in [v(5.0, 2.0), v(5.0, 8.0), v(-3.0, 1.0), v(2.0, 3.0), v(12.0, -4.0), v(7.0, 4.9)] {
+            let expected = brute_force_distance(&seg, p);
+            assert!((seg.distance(p) - expected).abs() < 1e-3, "{:?} {} {}", p, seg.distance(p), expected);
+        }
+    }
+
+    #[test]
+    fn cubic_distance() {
+        let seg = Segment::Cubic(v(0.0, 0.0), v(2.0, 8.0), v(8.0, -8.0), v(10.0, 0.0));
+        for p in [v(5.0, 0.5), v(1.0, 4.0), v(9.0, -4.0), v(-2.0, -2.0), v(5.0, 5.0), v(11.0, 1.0)] {
+            let expected = brute_force_distance(&seg, p);
+            assert!((seg.distance(p) - expected).abs() < 1e-3, "{:?} {} {}", p, seg.distance(p), expected);
+        }
+    }
+
+    #[test]
+    fn curved_winding() {
+        // lens shape from two quads
+        let lens = Contour { segments: vec![
+            Segment::Quad(v(0.0, 0.0), v(5.0, 6.0), v(10.0, 0.0)),
+            Segment::Quad(v(10.0, 0.0), v(5.0, -6.0), v(0.0, 0.0)),
+        ]};
+        let shape = Shape { contours: vec![lens] };
+
+        assert!(shape.is_inside(v(5.0, 2.9)));
+        assert!(!shape.is_inside(v(5.0, 3.1)));
+        assert!(shape.is_inside(v(5.0, -2.9)));
+        assert!(!shape.is_inside(v(-1.0, 0.0)));
+    }
+
+    #[test]
+    fn cubic_roots() {
+        // (t - 1)(t - 2)(t + 3) = t^3 - 7t + 6
+        let mut roots = solve_cubic(1.0, 0.0, -7.0, 6.0);
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(3, roots.len());
+        for (r, e) in roots.iter().zip([-3.0, 1.0, 2.0]) {
+            assert!((r - e).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn glyph_outline() {
+        let lib = freetype::Library::init().unwrap();
+        let face = lib.new_face(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf"), 0).unwrap();
+        face.set_pixel_sizes(64, 64).unwrap();
+        face.load_char('O' as usize, freetype::face::LoadFlag::NO_HINTING).unwrap();
+
+        let shape = Shape::from_outline(&face.glyph().outline().unwrap());
+        assert_eq!(2, shape.contours.len());
+
+        // center of the O is the hole
+        let m = face.glyph().metrics();
+        let center = v((m.horiBearingX + m.width / 2) as f64 / 64.0, (m.horiBearingY - m.height / 2) as f64 / 64.0);
+        assert!(shape.signed_distance(center) < -5.0);
+
+        // left side of the ring
+        let left = v((m.horiBearingX as f64 / 64.0) + 2.0, center.y);
+        assert!(shape.signed_distance(left) > 0.0);
+    }
+}
@@ -1,21 +1,50 @@
 use crate::ttf::*;
 
-#[repr(C, packed)]
 #[derive(Debug, Clone)]
 pub struct CmapTable {
-    pub index: CmapIndex,
     pub sub_tables: Vec<CmapSubTable>,
 }
 
+impl CmapTable {
+
+    /// Glyph id of the codepoint from the first subtable that has it, 0 is the missing glyph.
+    /// The subtables are sorted best first, so the full Unicode ones are tried before the BMP only ones
+    pub fn get_glyph_id(&self, codepoint: u32) -> u16 {
+        self.sub_tables.iter()
+            .map(|st| st.get_glyph_id(codepoint))
+            .find(|&id| id != 0)
+            .unwrap_or(0)
+    }
+
+    /// Glyph id of the variation sequence of base and the variation selector, 0 when the font
+    /// does not have the sequence. Sequences with the default glyph use the glyph of base
+    pub fn get_variant_glyph_id(&self, base: u32, selector: u32) -> u16 {
+        let variant = self.sub_tables.iter()
+            .find_map(|st| match &st.format {
+                Format::V14(f14) => f14.get_variant_glyph(base, selector),
+                _ => None
+            });
+
+        match variant {
+            Some(VariantGlyph::Default) => self.get_glyph_id(base),
+            Some(VariantGlyph::Glyph(id)) => id,
+            None => 0
+        }
+    }
+}
+
 impl Read for CmapTable {
     fn read(stream: &mut Stream) -> Option<Self> {
         stream.base = stream.offset;
-        let idx = stream.read::<CmapIndex>()?;
+
+        // the version is never checked
+        stream.offset += 2;
+        let num_subtables = stream.read::<u16>()?;
         let base_offset = stream.offset;
 
         let mut sub_tables = vec![];
-        for i in 0..idx.num_subtables as usize {
-            stream.offset = base_offset + i * std::mem::size_of::<CmapEncoding>();
+        for i in 0..num_subtables as usize {
+            stream.offset = base_offset + i * CmapEncoding::SIZE;
             let sub_table = CmapSubTable::read(stream);
 
             if let Some(st) = sub_table {
@@ -23,36 +52,15 @@ impl Read for CmapTable {
             }
         }
 
+        // stable, so subtables of the same rank keep the font's order
+        sub_tables.sort_by_key(CmapSubTable::rank);
+
         Some(CmapTable {
-            index: idx,
             sub_tables,
         })
     }
 }
 
-#[repr(C, packed)]
-#[derive(Debug, Clone)]
-pub struct CmapIndex {
-    version: u16,
-    num_subtables: u16,
-}
-
-impl FromData for CmapIndex {
-    fn parse(data: &[u8]) -> Option<Self> {
-        let mut stream = Stream {
-            data,
-            offset: 0,
-            base: 0,
-        };
-
-        Some(CmapIndex {
-            version: stream.read::<u16>()?,
-            num_subtables: stream.read::<u16>()?,
-        })
-    }
-}
-
-#[repr(C, packed)]
 #[derive(Debug, Clone)]
 pub struct CmapEncoding {
     platform_id: u16,
@@ -60,6 +68,16 @@ pub struct CmapEncoding {
     offset: u32,
 }
 
+impl CmapEncoding {
+    const SIZE: usize = 8;
+
+    /// Unicode platform, or the Windows Unicode BMP (3,1) and full repertoire (3,10) encodings.
+    /// Other platforms use legacy encodings where codepoints are not Unicode
+    fn is_unicode(&self) -> bool {
+        matches!((self.platform_id, self.platform_specifier_id), (0, _) | (3, 1) | (3, 10))
+    }
+}
+
 impl Read for CmapEncoding {
     fn read(stream: &mut Stream) -> Option<Self> {
         Some(CmapEncoding {
@@ -70,7 +88,6 @@ impl Read for CmapEncoding {
     }
 }
 
-#[repr(C, packed)]
 #[derive(Debug, Clone)]
 pub struct CmapSubTable {
     encoding: CmapEncoding,
@@ -80,8 +97,37 @@ pub struct CmapSubTable {
 
 impl CmapSubTable {
 
-    pub fn get_glyph_id(&self, data: &u8) -> usize {
-        0
+    /// Glyph id of the codepoint, 0 is the missing glyph
+    pub fn get_glyph_id(&self, codepoint: u32) -> u16 {
+        match &self.format {
+            Format::V0(f0) => f0.get_glyph_id(codepoint),
+            Format::V4(f4) => f4.get_glyph_id(codepoint),
+            Format::V6(f6) => f6.get_glyph_id(codepoint),
+            Format::V12(groups) | Format::V13(groups) => groups.get_glyph_id(codepoint),
+            // only maps variation sequences
+            Format::V14(_) => 0,
+        }
+    }
+
+    /// Format 12 and 13 cover all planes, as should the full repertoire encodings
+    pub fn is_full_unicode(&self) -> bool {
+        matches!(self.format, Format::V12(_) | Format::V13(_))
+            || matches!((self.encoding.platform_id, self.encoding.platform_specifier_id), (0, 4) | (0, 6) | (3, 10))
+    }
+
+    /// Selection order, lower is better. Full Unicode first, then the Windows BMP mapping that
+    /// Windows only fonts ship, then the other Unicode platform encodings. The variation
+    /// sequences subtable has no plain mappings and comes last
+    fn rank(&self) -> u8 {
+        if let Format::V14(_) = self.format {
+            3
+        } else if self.is_full_unicode() {
+            0
+        } else if self.encoding.platform_id == 3 {
+            1
+        } else {
+            2
+        }
     }
 }
 
@@ -90,11 +136,7 @@ impl Read for CmapSubTable {
         let encoding = CmapEncoding::read(stream)?;
 
         // Ignore all but unicode
-        if encoding.platform_id != 0 {
-            println!(
-                "Ignore platformId = {}, specifier={}",
-                encoding.platform_id, encoding.platform_specifier_id
-            );
+        if !encoding.is_unicode() {
             return None;
         }
 
@@ -104,50 +146,216 @@ impl Read for CmapSubTable {
         let format_id = stream.peek::<u16>()?;
 
         let format = match format_id {
+            0 => Format::V0(Format0::read(stream)?),
+            6 => Format::V6(Format6::read(stream)?),
             4 => {
-                let f4 = stream.read::<Format4>()?;
+                let f4 = Format4::read(stream)?;
                 Format::V4(f4)
             }
-            _ => {
-                println!("Only format 4 is supported, ignoring this: {:?}", format_id);
-                return None;
-            }
+            12 => Format::V12(FormatGroups::read(stream)?),
+            13 => Format::V13(FormatGroups::read(stream)?),
+            14 => Format::V14(Format14::read(stream)?),
+            // formats 2, 8 and 10 are for legacy multi byte encodings, not unicode
+            _ => return None,
         };
 
-        println!(
-            "{}, {}, {:#?}",
-            encoding.platform_id, encoding.platform_specifier_id, format
-        );
         Some(CmapSubTable { encoding, format})
     }
 }
 
-#[repr(C)]
 #[derive(Debug, Clone)]
 pub enum Format {
-    N,
+    V0(Format0),
     V4(Format4),
+    V6(Format6),
+    V12(FormatGroups),
+    V13(FormatGroups),
+    V14(Format14),
+}
+
+/// Byte encoding table, glyph ids of the first 256 codepoints
+#[derive(Debug, Clone)]
+pub struct Format0 {
+    glyph_index_array: Vec<u8>,
+}
+
+impl Format0 {
+
+    pub fn get_glyph_id(&self, codepoint: u32) -> u16 {
+        self.glyph_index_array.get(codepoint as usize).map_or(0, |&g| g as u16)
+    }
+}
+
+impl Read for Format0 {
+    fn read(stream: &mut Stream) -> Option<Self> {
+        // format, length and language
+        stream.offset += 6;
+
+        Some(Format0 {
+            glyph_index_array: stream.read_array::<u8>(256)?,
+        })
+    }
+}
+
+/// Trimmed table mapping, glyph ids of a single range of BMP codepoints
+#[derive(Debug, Clone)]
+pub struct Format6 {
+    first_code: u16,
+    glyph_index_array: Vec<u16>,
+}
+
+impl Format6 {
+
+    pub fn get_glyph_id(&self, codepoint: u32) -> u16 {
+        codepoint.checked_sub(self.first_code as u32)
+            .and_then(|i| self.glyph_index_array.get(i as usize))
+            .map_or(0, |&g| g)
+    }
+}
+
+impl Read for Format6 {
+    fn read(stream: &mut Stream) -> Option<Self> {
+        // format, length and language
+        stream.offset += 6;
+        let first_code = stream.read::<u16>()?;
+        let entry_count = stream.read::<u16>()?;
+        let glyph_index_array = stream.read_array::<u16>(entry_count as usize)?;
+
+        Some(Format6 {
+            first_code,
+            glyph_index_array,
+        })
+    }
 }
 
-#[repr(C, packed)]
+/// Segment mapping to delta values. The BMP is split in segments of consecutive codepoints,
+/// each mapped with a delta or through the glyph index array
 #[derive(Debug, Clone)]
 pub struct Format4 {
+    end_code: Vec<u16>,
+    start_code: Vec<u16>,
+    id_delta: Vec<i16>,
+    id_range_offset: Vec<u16>,
+    glyph_index_array: Vec<u16>,
+}
+
+impl Format4 {
+
+    pub fn get_glyph_id(&self, codepoint: u32) -> u16 {
+        if codepoint > 0xFFFF {
+            return 0;
+        }
+        let c = codepoint as u16;
+
+        // segments are sorted by end code, the last one ends at 0xFFFF
+        let seg = match self.end_code.iter().position(|&end| end >= c) {
+            Some(seg) if self.start_code[seg] <= c => seg,
+            _ => return 0
+        };
+
+        let delta = self.id_delta[seg] as u16;
+        let range_offset = self.id_range_offset[seg];
+
+        if range_offset == 0 {
+            return c.wrapping_add(delta);
+        }
+
+        // the offset is in bytes from the id_range_offset entry itself, the glyph index array follows the id_range_offset array
+        let index = seg + range_offset as usize / 2 + (c - self.start_code[seg]) as usize;
+        match index.checked_sub(self.end_code.len()).and_then(|i| self.glyph_index_array.get(i)) {
+            Some(0) | None => 0,
+            Some(&glyph) => glyph.wrapping_add(delta),
+        }
+    }
+}
+
+impl Read for Format4 {
+    fn read(stream: &mut Stream) -> Option<Self> {
+        let start = stream.offset;
+
+        // format
+        stream.offset += 2;
+        let length = stream.read::<u16>()?;
+        // language
+        stream.offset += 2;
+        let seg_count_x2 = stream.read::<u16>()?;
+        // the binary search fields, the lookup searches the segments itself
+        stream.offset += 6;
+
+        let seg_count = seg_count_x2 as usize / 2;
+        let end_code = stream.read_array::<u16>(seg_count)?;
+        // reserved pad
+        stream.offset += 2;
+        let start_code = stream.read_array::<u16>(seg_count)?;
+        let id_delta = stream.read_array::<i16>(seg_count)?;
+        let id_range_offset = stream.read_array::<u16>(seg_count)?;
+
+        // the rest of the subtable
+        let glyph_count = (start + length as usize).checked_sub(stream.offset)? / 2;
+        let glyph_index_array = stream.read_array::<u16>(glyph_count)?;
+
+        Some(Format4 {
+            end_code,
+            start_code,
+            id_delta,
+            id_range_offset,
+            glyph_index_array,
+        })
+    }
+}
+
+
+/// Format 12 segmented coverage and format 13 many-to-one range mappings, groups of 32 bit codepoint ranges
+#[derive(Debug, Clone)]
+pub struct FormatGroups {
     format: u16,
-    length: u16,
-    lang: u16,
-    seg_count_x2: u16,
-    search_range: u16,
-    entry_select: u16,
-    range_shifter: u16,
-    end_code: u16,
-    reserved_pad: u16,
-    start_code: u16,
-    id_delta: u16,
-    id_range_offset: u16,
-    glyph_index_array: u16,
-}
-
-impl FromData for Format4 {
+    groups: Vec<MapGroup>,
+}
+
+impl FormatGroups {
+
+    pub fn get_glyph_id(&self, codepoint: u32) -> u16 {
+        // groups are sorted by start code and do not overlap
+        let i = self.groups.partition_point(|g| g.end_char_code < codepoint);
+        match self.groups.get(i) {
+            Some(g) if g.start_char_code <= codepoint => {
+                // format 13 maps the whole range to the same glyph
+                let glyph = if self.format == 12 {
+                    g.glyph_id.wrapping_add(codepoint - g.start_char_code)
+                } else {
+                    g.glyph_id
+                };
+                glyph as u16
+            },
+            _ => 0
+        }
+    }
+}
+
+impl Read for FormatGroups {
+    fn read(stream: &mut Stream) -> Option<Self> {
+        let format = stream.read::<u16>()?;
+        // reserved, length and language
+        stream.offset += 10;
+        let num_groups = stream.read::<u32>()?;
+        let groups = stream.read_array::<MapGroup>(num_groups as usize)?;
+
+        Some(FormatGroups {
+            format,
+            groups,
+        })
+    }
+}
+
+/// Start glyph id of the range for format 12, the glyph id of all chars for format 13
+#[derive(Debug, Clone)]
+pub struct MapGroup {
+    start_char_code: u32,
+    end_char_code: u32,
+    glyph_id: u32,
+}
+
+impl FromData for MapGroup {
     fn parse(data: &[u8]) -> Option<Self> {
         let mut stream = Stream {
             data,
@@ -155,20 +363,406 @@ impl FromData for Format4 {
             base: 0,
         };
 
-        Some(Format4 {
-            format: stream.read::<u16>()?,
-            length: stream.read::<u16>()?,
-            lang: stream.read::<u16>()?,
-            seg_count_x2: stream.read::<u16>()?,
-            search_range: stream.read::<u16>()?,
-            entry_select: stream.read::<u16>()?,
-            range_shifter: stream.read::<u16>()?,
-            end_code: stream.read::<u16>()?,
-            reserved_pad: stream.read::<u16>()?,
-            start_code: stream.read::<u16>()?,
-            id_delta: stream.read::<u16>()?,
-            id_range_offset: stream.read::<u16>()?,
-            glyph_index_array: stream.read::<u16>()?,
+        Some(MapGroup {
+            start_char_code: stream.read::<u32>()?,
+            end_char_code: stream.read::<u32>()?,
+            glyph_id: stream.read::<u32>()?,
+        })
+    }
+}
+
+
+/// Unicode variation sequences, glyphs of a base char followed by a variation selector
+#[derive(Debug, Clone)]
+pub struct Format14 {
+    var_selectors: Vec<VariationSelector>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VariantGlyph {
+    /// The sequence uses the glyph the other subtables map base to
+    Default,
+    Glyph(u16),
+}
+
+impl Format14 {
+
+    pub fn get_variant_glyph(&self, base: u32, selector: u32) -> Option<VariantGlyph> {
+        // records are sorted by selector, default ranges and mappings by codepoint
+        let i = self.var_selectors.binary_search_by_key(&selector, |vs| vs.var_selector).ok()?;
+        let vs = &self.var_selectors[i];
+
+        let r = vs.default_uvs.partition_point(|r| r.start_unicode_value + (r.additional_count as u32) < base);
+        if vs.default_uvs.get(r).is_some_and(|r| r.start_unicode_value <= base) {
+            return Some(VariantGlyph::Default);
+        }
+
+        let m = vs.non_default_uvs.binary_search_by_key(&base, |m| m.unicode_value).ok()?;
+        Some(VariantGlyph::Glyph(vs.non_default_uvs[m].glyph_id))
+    }
+}
+
+impl Read for Format14 {
+    fn read(stream: &mut Stream) -> Option<Self> {
+        // the uvs offsets are from the start of the subtable
+        let start = stream.offset;
+
+        // format and length
+        stream.offset += 6;
+        let num_var_selector_records = stream.read::<u32>()?;
+
+        let mut var_selectors = vec![];
+        for i in 0..num_var_selector_records as usize {
+            stream.offset = start + 10 + i * 11;
+            let var_selector = stream.read::<U24>()?.0;
+            let default_uvs_offset = stream.read::<u32>()? as usize;
+            let non_default_uvs_offset = stream.read::<u32>()? as usize;
+
+            // an offset of 0 means there is no table
+            let mut default_uvs = vec![];
+            if default_uvs_offset != 0 {
+                stream.offset = start + default_uvs_offset;
+                let count = stream.read::<u32>()?;
+                default_uvs = stream.read_array::<UnicodeRange>(count as usize)?;
+            }
+
+            let mut non_default_uvs = vec![];
+            if non_default_uvs_offset != 0 {
+                stream.offset = start + non_default_uvs_offset;
+                let count = stream.read::<u32>()?;
+                non_default_uvs = stream.read_array::<UvsMapping>(count as usize)?;
+            }
+
+            var_selectors.push(VariationSelector { var_selector, default_uvs, non_default_uvs });
+        }
+
+        Some(Format14 {
+            var_selectors,
         })
     }
 }
+
+/// Sequences of one variation selector
+#[derive(Debug, Clone)]
+pub struct VariationSelector {
+    var_selector: u32,
+    default_uvs: Vec<UnicodeRange>,
+    non_default_uvs: Vec<UvsMapping>,
+}
+
+/// Base chars start_unicode_value..=start_unicode_value + additional_count
+#[derive(Debug, Clone)]
+pub struct UnicodeRange {
+    start_unicode_value: u32,
+    additional_count: u8,
+}
+
+impl FromData for UnicodeRange {
+    const SIZE: usize = 4;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut stream = Stream {
+            data,
+            offset: 0,
+            base: 0,
+        };
+
+        Some(UnicodeRange {
+            start_unicode_value: stream.read::<U24>()?.0,
+            additional_count: stream.read::<u8>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UvsMapping {
+    unicode_value: u32,
+    glyph_id: u16,
+}
+
+impl FromData for UvsMapping {
+    const SIZE: usize = 5;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut stream = Stream {
+            data,
+            offset: 0,
+            base: 0,
+        };
+
+        Some(UvsMapping {
+            unicode_value: stream.read::<U24>()?.0,
+            glyph_id: stream.read::<u16>()?,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn font_data(file: &str) -> Vec<u8> {
+        std::fs::read(format!("{}/test_fonts/{}", env!("CARGO_MANIFEST_DIR"), file)).unwrap()
+    }
+
+    fn format4(segments: &[(u16, u16, i16, u16)], glyph_index_array: &[u16]) -> Format4 {
+        Format4 {
+            end_code: segments.iter().map(|s| s.1).collect(),
+            start_code: segments.iter().map(|s| s.0).collect(),
+            id_delta: segments.iter().map(|s| s.2).collect(),
+            id_range_offset: segments.iter().map(|s| s.3).collect(),
+            glyph_index_array: glyph_index_array.to_vec(),
+        }
+    }
+
+    #[test]
+    fn format4_lookup() {
+        // 'A'..'C' by delta, 'a'..'b' through the glyph index array, and the closing 0xFFFF segment
+        let f4 = format4(&[(65, 67, -60, 0), (97, 98, 0, 4), (0xFFFF, 0xFFFF, 1, 0)], &[20, 0]);
+
+        assert_eq!(5, f4.get_glyph_id('A' as u32));
+        assert_eq!(7, f4.get_glyph_id('C' as u32));
+        assert_eq!(20, f4.get_glyph_id('a' as u32));
+        assert_eq!(0, f4.get_glyph_id('b' as u32));
+        assert_eq!(0, f4.get_glyph_id('D' as u32));
+        assert_eq!(0, f4.get_glyph_id(0x1F600));
+        assert_eq!(0, f4.get_glyph_id(0xFFFF));
+    }
+
+    // format 4 subtable with only delta mapped segments (start, end, delta)
+    fn format4_bytes(segments: &[(u16, u16, i16)]) -> Vec<u8> {
+        let mut res = vec![];
+        let seg_count = segments.len() as u16;
+        for v in [4, 16 + 8 * seg_count, 0, 2 * seg_count, 0, 0, 0] {
+            res.extend(v.to_be_bytes());
+        }
+        segments.iter().for_each(|s| res.extend(s.1.to_be_bytes()));
+        res.extend([0, 0]);
+        segments.iter().for_each(|s| res.extend(s.0.to_be_bytes()));
+        segments.iter().for_each(|s| res.extend(s.2.to_be_bytes()));
+        segments.iter().for_each(|_| res.extend([0, 0]));
+        res
+    }
+
+    // format 12 or 13 subtable with (start, end, glyph) groups
+    fn groups_bytes(format: u16, groups: &[(u32, u32, u32)]) -> Vec<u8> {
+        let mut res = vec![];
+        res.extend(format.to_be_bytes());
+        res.extend([0, 0]);
+        for v in [16 + 12 * groups.len() as u32, 0, groups.len() as u32] {
+            res.extend(v.to_be_bytes());
+        }
+        for g in groups {
+            for v in [g.0, g.1, g.2] {
+                res.extend(v.to_be_bytes());
+            }
+        }
+        res
+    }
+
+    // cmap table with the (platform, encoding, subtable) entries
+    fn cmap_bytes(sub_tables: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut res = vec![0, 0];
+        res.extend((sub_tables.len() as u16).to_be_bytes());
+
+        let mut offset = 4 + CmapEncoding::SIZE * sub_tables.len();
+        for (platform, encoding, data) in sub_tables {
+            res.extend(platform.to_be_bytes());
+            res.extend(encoding.to_be_bytes());
+            res.extend((offset as u32).to_be_bytes());
+            offset += data.len();
+        }
+        sub_tables.iter().for_each(|st| res.extend(&st.2));
+        res
+    }
+
+    fn u24(v: u32) -> [u8; 3] {
+        let [_, a, b, c] = v.to_be_bytes();
+        [a, b, c]
+    }
+
+    // selector, default (start, additional count) ranges and (base, glyph) mappings
+    type UvsRecord<'a> = (u32, &'a [(u32, u8)], &'a [(u32, u16)]);
+
+    fn format14_bytes(records: &[UvsRecord]) -> Vec<u8> {
+        let header_len = 10 + 11 * records.len();
+        let mut tables = vec![];
+        let mut res = vec![0, 14];
+        res.extend([0; 4]);
+        res.extend((records.len() as u32).to_be_bytes());
+
+        for (selector, default_uvs, non_default_uvs) in records {
+            res.extend(u24(*selector));
+
+            if default_uvs.is_empty() {
+                res.extend(0u32.to_be_bytes());
+            } else {
+                res.extend(((header_len + tables.len()) as u32).to_be_bytes());
+                tables.extend((default_uvs.len() as u32).to_be_bytes());
+                for r in default_uvs.iter() {
+                    tables.extend(u24(r.0));
+                    tables.push(r.1);
+                }
+            }
+
+            if non_default_uvs.is_empty() {
+                res.extend(0u32.to_be_bytes());
+            } else {
+                res.extend(((header_len + tables.len()) as u32).to_be_bytes());
+                tables.extend((non_default_uvs.len() as u32).to_be_bytes());
+                for m in non_default_uvs.iter() {
+                    tables.extend(u24(m.0));
+                    tables.extend(m.1.to_be_bytes());
+                }
+            }
+        }
+
+        res.extend(tables);
+        let length = res.len() as u32;
+        res[2..6].copy_from_slice(&length.to_be_bytes());
+        res
+    }
+
+    fn read_cmap(data: &[u8]) -> CmapTable {
+        CmapTable::read(&mut Stream { data, offset: 0, base: 0 }).unwrap()
+    }
+
+    #[test]
+    fn format12_lookup() {
+        let cmap = read_cmap(&cmap_bytes(&[(0, 4, groups_bytes(12, &[(0x41, 0x43, 5), (0x1F600, 0x1F64F, 100)]))]));
+
+        assert_eq!(1, cmap.sub_tables.len());
+        assert_eq!(5, cmap.get_glyph_id(0x41));
+        assert_eq!(7, cmap.get_glyph_id(0x43));
+        assert_eq!(100, cmap.get_glyph_id(0x1F600));
+        assert_eq!(179, cmap.get_glyph_id(0x1F64F));
+        assert_eq!(0, cmap.get_glyph_id(0x44));
+        assert_eq!(0, cmap.get_glyph_id(0x1F650));
+        assert_eq!(0, cmap.get_glyph_id(0x20));
+    }
+
+    #[test]
+    fn format13_lookup() {
+        let cmap = read_cmap(&cmap_bytes(&[(0, 6, groups_bytes(13, &[(0, 0x10FFFF, 1)]))]));
+
+        assert_eq!(1, cmap.get_glyph_id(0x41));
+        assert_eq!(1, cmap.get_glyph_id(0x10FFFF));
+    }
+
+    #[test]
+    fn prefer_full_unicode() {
+        let cmap = read_cmap(&cmap_bytes(&[
+            (0, 3, format4_bytes(&[(0x41, 0x5A, -60), (0x61, 0x61, -90), (0xFFFF, 0xFFFF, 1)])),
+            (0, 4, groups_bytes(12, &[(0x41, 0x5A, 10), (0x1F600, 0x1F600, 40)])),
+        ]));
+
+        assert_eq!(2, cmap.sub_tables.len());
+        assert!(cmap.sub_tables[0].is_full_unicode());
+        assert!(!cmap.sub_tables[1].is_full_unicode());
+
+        assert_eq!(10, cmap.get_glyph_id(0x41));
+        assert_eq!(40, cmap.get_glyph_id(0x1F600));
+        // falls back to the BMP subtable for chars the full one lacks
+        assert_eq!(7, cmap.get_glyph_id(0x61));
+    }
+
+    #[test]
+    fn format0_lookup() {
+        let mut data = vec![0, 0, 1, 6, 0, 0];
+        data.extend((0..=255u8).map(|c| if c.is_ascii_uppercase() { c - 60 } else { 0 }));
+        let cmap = read_cmap(&cmap_bytes(&[(0, 3, data)]));
+
+        assert_eq!(5, cmap.get_glyph_id(0x41));
+        assert_eq!(30, cmap.get_glyph_id(0x5A));
+        assert_eq!(0, cmap.get_glyph_id(0x61));
+        assert_eq!(0, cmap.get_glyph_id(0x141));
+    }
+
+    #[test]
+    fn format6_lookup() {
+        let mut data = vec![];
+        for v in [6u16, 16, 0, 0x41, 3, 5, 0, 7] {
+            data.extend(v.to_be_bytes());
+        }
+        let cmap = read_cmap(&cmap_bytes(&[(3, 1, data)]));
+
+        assert_eq!(5, cmap.get_glyph_id(0x41));
+        assert_eq!(0, cmap.get_glyph_id(0x42));
+        assert_eq!(7, cmap.get_glyph_id(0x43));
+        assert_eq!(0, cmap.get_glyph_id(0x40));
+        assert_eq!(0, cmap.get_glyph_id(0x44));
+    }
+
+    #[test]
+    fn select_windows_and_full_unicode() {
+        let cmap = read_cmap(&cmap_bytes(&[
+            // mac roman is not unicode and is never used
+            (1, 0, format4_bytes(&[(0x41, 0x5A, 100), (0xFFFF, 0xFFFF, 1)])),
+            (0, 3, format4_bytes(&[(0x41, 0x5A, -60), (0x61, 0x61, -90), (0xFFFF, 0xFFFF, 1)])),
+            (3, 1, format4_bytes(&[(0x41, 0x5A, -50), (0xFFFF, 0xFFFF, 1)])),
+            (3, 10, groups_bytes(12, &[(0x1F600, 0x1F600, 40)])),
+        ]));
+
+        let encodings : Vec::<(u16, u16)> = cmap.sub_tables.iter()
+            .map(|st| (st.encoding.platform_id, st.encoding.platform_specifier_id))
+            .collect();
+        assert_eq!(vec![(3, 10), (3, 1), (0, 3)], encodings);
+
+        assert_eq!(40, cmap.get_glyph_id(0x1F600));
+        assert_eq!(15, cmap.get_glyph_id(0x41));
+        assert_eq!(7, cmap.get_glyph_id(0x61));
+    }
+
+    #[test]
+    fn windows_only_font() {
+        let cmap = read_cmap(&cmap_bytes(&[(3, 1, format4_bytes(&[(0x41, 0x5A, -60), (0xFFFF, 0xFFFF, 1)]))]));
+
+        assert_eq!(1, cmap.sub_tables.len());
+        assert_eq!(5, cmap.get_glyph_id(0x41));
+    }
+
+    #[test]
+    fn variation_sequences() {
+        let cmap = read_cmap(&cmap_bytes(&[
+            (0, 5, format14_bytes(&[
+                (0xFE0E, &[(0x2600, 4)], &[]),
+                (0xFE0F, &[], &[(0x2600, 50), (0x2764, 51)]),
+                (0xE0100, &[(0x845B, 0)], &[(0x8FBB, 60)]),
+            ])),
+            (3, 1, format4_bytes(&[(0x2600, 0x2604, -0x2600 + 10), (0x845B, 0x845B, 20u16.wrapping_sub(0x845B) as i16), (0xFFFF, 0xFFFF, 1)])),
+        ]));
+
+        // the variation sequences subtable comes last and does not map plain chars
+        assert_eq!(2, cmap.sub_tables.len());
+        assert!(matches!(cmap.sub_tables[1].format, Format::V14(_)));
+        assert_eq!(0, cmap.sub_tables[1].get_glyph_id(0x2600));
+
+        assert_eq!(12, cmap.get_variant_glyph_id(0x2602, 0xFE0E));
+        assert_eq!(14, cmap.get_variant_glyph_id(0x2604, 0xFE0E));
+        assert_eq!(0, cmap.get_variant_glyph_id(0x2605, 0xFE0E));
+        assert_eq!(50, cmap.get_variant_glyph_id(0x2600, 0xFE0F));
+        assert_eq!(51, cmap.get_variant_glyph_id(0x2764, 0xFE0F));
+        assert_eq!(0, cmap.get_variant_glyph_id(0x2601, 0xFE0F));
+        assert_eq!(20, cmap.get_variant_glyph_id(0x845B, 0xE0100));
+        assert_eq!(60, cmap.get_variant_glyph_id(0x8FBB, 0xE0100));
+        assert_eq!(0, cmap.get_variant_glyph_id(0x845B, 0xE0101));
+    }
+
+    #[test]
+    fn same_as_freetype() {
+        let lib = freetype::Library::init().unwrap();
+
+        for file in ["calibri.ttf", "calibrib.ttf", "calibrii.ttf", "calibril.ttf", "calibrili.ttf", "calibriz.ttf"] {
+            let data = font_data(file);
+            let font = parse_font(&data).unwrap();
+            let face = lib.new_face(format!("{}/test_fonts/{}", env!("CARGO_MANIFEST_DIR"), file), 0).unwrap();
+
+            assert!(!font.cmap_table.sub_tables.is_empty());
+            for c in 0..=0xFFFF {
+                assert_eq!(face.get_char_index(c as usize), font.cmap_table.get_glyph_id(c) as u32, "{} U+{:04X}", file, c);
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-pub type FWord = u16;
+pub type FWord = i16;
+// only hmtx uses it, which is only compiled for the tests
+#[cfg(test)]
+pub type UFWord = u16;
 pub type LongDateTime = u64;
 
 
@@ -21,13 +24,17 @@ pub struct Stream<'a> {
 impl<'a> Stream<'a> {
 
     pub fn read<T>(&mut self) -> Option<T> where T: FromData {
-        let res = T::parse(&self.data[self.offset..self.offset + T::SIZE]);
+        let res = T::parse(self.data.get(self.offset..self.offset + T::SIZE)?);
         self.offset += T::SIZE;
         res
     }
 
-     pub fn peek<T>(&mut self) -> Option<T> where T: FromData {
-        T::parse(&self.data[self.offset..self.offset + T::SIZE])
+    pub fn peek<T>(&mut self) -> Option<T> where T: FromData {
+        T::parse(self.data.get(self.offset..self.offset + T::SIZE)?)
+    }
+
+    pub fn read_array<T>(&mut self, count: usize) -> Option<Vec<T>> where T: FromData {
+        (0..count).map(|_| self.read::<T>()).collect()
     }
 }
 
@@ -52,6 +59,27 @@ impl FromData for Fixed {
 
 
 
+impl FromData for u8 {
+    fn parse(data: &[u8]) -> Option<Self> {
+        data.first().copied()
+    }
+}
+
+/// 24 bit unsigned, used for codepoints in cmap format 14
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct U24(pub u32);
+
+impl FromData for U24 {
+    const SIZE: usize = 3;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        match data {
+            &[a, b, c] => Some(U24(u32::from_be_bytes([0, a, b, c]))),
+            _ => None
+        }
+    }
+}
+
 impl FromData for u16 {
     fn parse(data: &[u8]) -> Option<Self> {
         data.try_into().ok().map(u16::from_be_bytes)
@@ -96,6 +124,26 @@ mod tests {
         assert_eq!(10, num.unwrap());
     }
 
+    #[test]
+    fn read_past_end() {
+        let data: [u8; 5] = [0, 1, 0, 2, 0];
+        let mut stream = Stream { data: &data, offset: 0, base: 0 };
+
+        assert_eq!(Some(vec![1, 2]), stream.read_array::<u16>(2));
+        assert_eq!(None, stream.read::<u16>());
+        assert_eq!(None, stream.read_array::<u16>(1));
+    }
+
+    #[test]
+    fn parse_u24() {
+        let data: [u8; 4] = [0x0E, 0x01, 0x00, 0xFF];
+        let mut stream = Stream { data: &data, offset: 0, base: 0 };
+
+        assert_eq!(Some(U24(0xE0100)), stream.read::<U24>());
+        assert_eq!(3, stream.offset);
+        assert_eq!(None, stream.read::<U24>());
+    }
+
     #[test]
     fn parse_u32() {
         let data: [u8; 12] = [0, 0, 0, 10, 0, 10, 0, 10, 0, 10, 0, 10];
@@ -1,7 +1,7 @@
 use crate::ttf::*;
 
 #[repr(C, packed)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct Table {
     version: Fixed,
     font_revision: Fixed,
@@ -67,7 +67,7 @@ mod test {
 
         let table = Table::parse(&data).unwrap();
 
-        assert_eq!(0x5F0F3CF5, table.magic_num);
+        assert_eq!(0x5F0F3CF5, { table.magic_num });
 
 
     }
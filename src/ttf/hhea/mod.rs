@@ -0,0 +1,40 @@
+use crate::ttf::*;
+
+/// Horizontal header, the line metrics in font units and the number of advances in hmtx
+#[derive(Debug, Clone, Copy)]
+pub struct Table {
+    pub ascender: FWord,
+    pub descender: FWord,
+    pub line_gap: FWord,
+    pub number_of_h_metrics: u16,
+}
+
+impl FromData for Table {
+    const SIZE: usize = 36;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+
+        let mut stream = Stream {
+            data,
+            offset: 0,
+            base: 0
+        };
+
+        // version
+        stream.offset += 4;
+        let ascender = stream.read::<FWord>()?;
+        let descender = stream.read::<FWord>()?;
+        let line_gap = stream.read::<FWord>()?;
+
+        // the extents and caret of the glyphs, the reserved fields and the metric data format
+        stream.offset = 34;
+        let number_of_h_metrics = stream.read::<u16>()?;
+
+        Some(Table {
+            ascender,
+            descender,
+            line_gap,
+            number_of_h_metrics,
+        })
+    }
+}
@@ -0,0 +1,82 @@
+use crate::ttf::*;
+
+/// Horizontal metrics of every glyph. Glyphs after the last long metric, usually the tail of a
+/// monospaced font, have the advance of the last one and only store their left side bearing
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub h_metrics: Vec<LongHorMetric>,
+    pub left_side_bearings: Vec<FWord>,
+}
+
+impl Table {
+
+    /// The counts come from hhea and maxp
+    pub fn read(stream: &mut Stream, number_of_h_metrics: u16, num_glyphs: u16) -> Option<Self> {
+        let h_metrics = stream.read_array::<LongHorMetric>(number_of_h_metrics as usize)?;
+        let left_side_bearings = stream.read_array::<FWord>(num_glyphs.saturating_sub(number_of_h_metrics) as usize)?;
+
+        Some(Table {
+            h_metrics,
+            left_side_bearings,
+        })
+    }
+
+    /// Advance in font units, 0 for fonts without metrics
+    pub fn advance_width(&self, glyph_id: u16) -> UFWord {
+        self.h_metrics.get(glyph_id as usize)
+            .or(self.h_metrics.last())
+            .map_or(0, |m| m.advance_width)
+    }
+
+    /// Left side bearing in font units, 0 for glyph ids past the font's glyph count
+    pub fn left_side_bearing(&self, glyph_id: u16) -> FWord {
+        match self.h_metrics.get(glyph_id as usize) {
+            Some(m) => m.lsb,
+            None => self.left_side_bearings.get(glyph_id as usize - self.h_metrics.len()).copied().unwrap_or(0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LongHorMetric {
+    pub advance_width: UFWord,
+    pub lsb: FWord,
+}
+
+impl FromData for LongHorMetric {
+    fn parse(data: &[u8]) -> Option<Self> {
+
+        let mut stream = Stream {
+            data,
+            offset: 0,
+            base: 0
+        };
+
+        Some(LongHorMetric {
+            advance_width: stream.read::<UFWord>()?,
+            lsb: stream.read::<FWord>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn monospaced_tail() {
+        let data: [u8; 12] = [0x02, 0x00, 0x00, 0x10, 0x04, 0x00, 0xFF, 0xF0, 0x00, 0x20, 0x00, 0x30];
+        let table = Table::read(&mut Stream { data: &data, offset: 0, base: 0 }, 2, 4).unwrap();
+
+        assert_eq!(512, table.advance_width(0));
+        assert_eq!(1024, table.advance_width(1));
+        assert_eq!(1024, table.advance_width(3));
+        assert_eq!(16, table.left_side_bearing(0));
+        assert_eq!(-16, table.left_side_bearing(1));
+        assert_eq!(48, table.left_side_bearing(3));
+        assert_eq!(0, table.left_side_bearing(4));
+
+        assert!(Table::read(&mut Stream { data: &data, offset: 0, base: 0 }, 2, 5).is_none());
+    }
+}
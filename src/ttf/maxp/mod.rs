@@ -0,0 +1,28 @@
+use crate::ttf::*;
+
+/// Maximum profile. Only the part every version has, version 1.0 fonts with TrueType outlines
+/// add limits for the glyph programs after the glyph count
+#[derive(Debug, Clone, Copy)]
+pub struct Table {
+    pub num_glyphs: u16,
+}
+
+impl FromData for Table {
+    const SIZE: usize = 6;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+
+        let mut stream = Stream {
+            data,
+            offset: 0,
+            base: 0
+        };
+
+        // the version only tells which fields follow the glyph count
+        stream.offset += 4;
+
+        Some(Table {
+            num_glyphs: stream.read::<u16>()?,
+        })
+    }
+}
@@ -1,15 +1,26 @@
-/// A true type Loading
+//! A true type Loading
 
 mod head;
 mod cmap;
+// the generator takes the metrics from freetype, these tables are only compared against it in the tests
+#[cfg(test)]
+mod maxp;
+#[cfg(test)]
+mod hhea;
+#[cfg(test)]
+mod hmtx;
 mod data_types;
 use data_types::*;
 
 #[derive(Debug, Clone)]
 pub struct FontInfo {
-    pub offset_table: OffsetTable,
-    pub head_table: head::Table,
-    pub cmap_table: cmap::CmapTable
+    pub cmap_table: cmap::CmapTable,
+    #[cfg(test)]
+    pub maxp_table: Option<maxp::Table>,
+    #[cfg(test)]
+    pub hhea_table: Option<hhea::Table>,
+    #[cfg(test)]
+    pub hmtx_table: Option<hmtx::Table>,
 }
 
 
@@ -19,40 +30,43 @@ pub fn parse_font(data: &[u8]) -> Option<FontInfo> {
 
     let offset_table = OffsetTable::parse(data)?;
 
+    let mut stream = Stream { data, offset: OffsetTable::SIZE, base: 0 };
+    let table_dirs = stream.read_array::<TableDir>(offset_table.num_tables as usize)?;
 
-    let mut head_table = None;
-    let mut cmap_table = None;
+    // offset of the table, the other tables are not read
+    let table_offset = |tag: &[u8; 4]| table_dirs.iter()
+        .find(|dir| dir.tag == u32::from_be_bytes(*tag))
+        .map(|dir| dir.offset as usize);
 
-    let base_offset = OffsetTable::SIZE;
+    // nothing in the head is used, but a font without one is not valid
+    stream.offset = table_offset(b"head")?;
+    stream.read::<head::Table>()?;
 
+    stream.offset = table_offset(b"cmap")?;
+    let cmap_table = cmap::CmapTable::read(&mut stream)?;
 
-    let mut stream = Stream { data, offset: 0, base: 0 };
+    // only the head and cmap are needed, the metrics tables are optional
+    #[cfg(test)]
+    let maxp_table = table_offset(b"maxp").and_then(|offset| Stream { data, offset, base: 0 }.read::<maxp::Table>());
+    #[cfg(test)]
+    let hhea_table = table_offset(b"hhea").and_then(|offset| Stream { data, offset, base: 0 }.read::<hhea::Table>());
 
-    for i  in 0..(offset_table.num_tables as usize) {
-        let offset = base_offset + i * TableDir::SIZE;
-        stream.offset = offset;
-        let table_dir = stream.read::<TableDir>()?;
-
-        stream.offset = table_dir.offset as usize;
-        match &table_dir.tag.to_be_bytes() {
-            b"head"  => {
-                head_table = stream.read::<head::Table>();
-            },
-
-            b"cmap"  => {
-                cmap_table = cmap::CmapTable::read(&mut stream);
-            },
-            unknown => {
-                println!("Unknown header: '{:?}' ignored", String::from_utf8( unknown.to_vec()));
-            }
-        }
-
-    }
+    // hmtx needs the counts from hhea and maxp
+    #[cfg(test)]
+    let hmtx_table = match (table_offset(b"hmtx"), &hhea_table, &maxp_table) {
+        (Some(offset), Some(hhea), Some(maxp)) =>
+            hmtx::Table::read(&mut Stream { data, offset, base: 0 }, hhea.number_of_h_metrics, maxp.num_glyphs),
+        _ => None
+    };
 
     Some(FontInfo {
-        offset_table,
-        head_table: head_table?,
-        cmap_table: cmap_table?,
+        cmap_table,
+        #[cfg(test)]
+        maxp_table,
+        #[cfg(test)]
+        hhea_table,
+        #[cfg(test)]
+        hmtx_table,
     })
 }
 
@@ -129,6 +143,53 @@ mod tests {
         assert_eq!(10, t.range_shift);
     }
 
+    #[test]
+    fn metrics_same_as_freetype() {
+        let lib = freetype::Library::init().unwrap();
+
+        for file in ["calibri.ttf", "calibrib.ttf", "calibriz.ttf"] {
+            let path = format!("{}/test_fonts/{}", env!("CARGO_MANIFEST_DIR"), file);
+            let font = parse_font(&std::fs::read(&path).unwrap()).unwrap();
+            let face = lib.new_face(&path, 0).unwrap();
+
+            let (maxp, hhea, hmtx) = (font.maxp_table.unwrap(), font.hhea_table.unwrap(), font.hmtx_table.unwrap());
+
+            assert_eq!(face.num_glyphs() as u16, maxp.num_glyphs);
+            assert_eq!(face.ascender(), hhea.ascender);
+            assert_eq!(face.descender(), hhea.descender);
+            assert_eq!(face.height(), hhea.ascender - hhea.descender + hhea.line_gap);
+            assert_eq!(face.num_glyphs() as usize, hmtx.h_metrics.len() + hmtx.left_side_bearings.len());
+
+            for glyph in 0..maxp.num_glyphs {
+                face.load_glyph(glyph as u32, freetype::face::LoadFlag::NO_SCALE).unwrap();
+                assert_eq!(face.glyph().metrics().horiAdvance, hmtx.advance_width(glyph) as _, "{} glyph {}", file, glyph);
+            }
+        }
+    }
+
+    #[test]
+    fn only_head_and_cmap() {
+        let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/test_fonts/calibri.ttf")).unwrap();
+
+        // keep only the head and cmap entries of the table directory, the table data stays where it is
+        let num_tables = u16::from_be_bytes([data[4], data[5]]) as usize;
+        let dirs : Vec::<&[u8]> = (0..num_tables)
+            .map(|i| &data[OffsetTable::SIZE + i * TableDir::SIZE..][..TableDir::SIZE])
+            .filter(|dir| &dir[0..4] == b"head" || &dir[0..4] == b"cmap")
+            .collect();
+        assert_eq!(2, dirs.len());
+
+        let mut stripped = data.clone();
+        stripped[4..6].copy_from_slice(&2u16.to_be_bytes());
+        stripped[OffsetTable::SIZE..][..2 * TableDir::SIZE].copy_from_slice(&dirs.concat());
+
+        let font = parse_font(&stripped).unwrap();
+        assert!(font.maxp_table.is_none());
+        assert!(font.hhea_table.is_none());
+        assert!(font.hmtx_table.is_none());
+        assert_ne!(0, font.cmap_table.get_glyph_id('A' as u32));
+    }
+
     #[test]
     fn parse_table_none() {
         let data: [u8; 9] = [0, 0, 0, 10, 0, 10, 0, 10, 0];